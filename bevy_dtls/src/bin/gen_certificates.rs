@@ -0,0 +1,89 @@
+use std::{env, path::PathBuf};
+use anyhow::bail;
+use bevy_dtls::cert::generator::{LocalCa, PemPair};
+
+const USAGE: &str = "\
+usage: gen_certificates [options]
+    --out <dir>       output directory (default: my_certificates)
+    --san <name>      subject alt name of server, repeatable (default: webrtc.rs)
+    --client <name>   client certificate name, repeatable (default: client)
+    --new-ca          generate new ca even if ca.priv.pem and ca.pub.pem exist
+";
+
+struct Args {
+    out: PathBuf,
+    sans: Vec<String>,
+    clients: Vec<String>,
+    new_ca: bool
+}
+
+fn parse_args() -> anyhow::Result<Args> {
+    let mut out = PathBuf::from("my_certificates");
+    let mut sans = vec![];
+    let mut clients = vec![];
+    let mut new_ca = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--out" => match args.next() {
+                Some(v) => out = v.into(),
+                None => bail!("--out requires value")
+            },
+            "--san" => match args.next() {
+                Some(v) => sans.push(v),
+                None => bail!("--san requires value")
+            },
+            "--client" => match args.next() {
+                Some(v) => clients.push(v),
+                None => bail!("--client requires value")
+            },
+            "--new-ca" => new_ca = true,
+            a => bail!("unknown argument {a}\n{USAGE}")
+        }
+    }
+
+    if sans.is_empty() {
+        sans.push("webrtc.rs".to_string());
+    }
+    if clients.is_empty() {
+        clients.push("client".to_string());
+    }
+
+    Ok(Args{
+        out,
+        sans,
+        clients,
+        new_ca
+    })
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = parse_args()?;
+
+    let ca = match PemPair::read(&args.out, "ca") {
+        Ok(pem) if !args.new_ca => {
+            println!("using existing ca in {}", args.out.display());
+            LocalCa::from_pem(&pem)?
+        }
+        _ => {
+            let ca = LocalCa::generate("bevy_dtls local ca")?;
+            ca.to_pem()
+            .write(&args.out, "ca")?;
+            println!("generated ca.priv.pem, ca.pub.pem");
+            ca
+        }
+    };
+
+    ca.issue_server(args.sans.clone())?
+    .write(&args.out, "server")?;
+    println!("generated server.priv.pem, server.pub.pem for {:?}", args.sans);
+
+    for client in args.clients.iter() {
+        ca.issue_client(client)?
+        .write(&args.out, client)?;
+        println!("generated {client}.priv.pem, {client}.pub.pem");
+    }
+
+    Ok(())
+}
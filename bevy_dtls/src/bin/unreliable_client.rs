@@ -18,6 +18,10 @@ fn send_hellooon_system(
     dtls_client: Res<DtlsClient>, 
     mut counter: ResMut<ClientHellooonCounter>
 ) {
+    if dtls_client.state() != DtlsClientState::Connected {
+        return;
+    }
+
     let str = format!("from client helloooooon {}", counter.0);
     let msg = Bytes::from(str);
     match dtls_client.send(msg) {
@@ -62,7 +66,8 @@ impl Plugin for ClientPlugin {
             server_port: self.server_port,
             client_addr: self.client_addr, 
             client_port: self.client_port,
-            cert_option: self.cert_option.clone()
+            cert_option: self.cert_option.clone(),
+            tuning: default()
         }) {
             panic!("{e}")
         }
@@ -85,7 +90,12 @@ fn main() {
         }),
         DtlsClientPlugin{
             buf_size: 512,
-            timeout_secs: 10
+            timeout_secs: 10,
+            recv_timeout_secs: None,
+            heartbeat_secs: Some(5),
+            queue: default(),
+            ping_secs: Some(1),
+            reconnect: Some(default())
         }
     ))
     .add_plugins(
@@ -62,7 +62,10 @@ impl Plugin for SereverPlugin {
         if let Err(e) = dtls_server.start(DtlsServerConfig{
             listen_addr: self.listen_addr,
             listen_port: self.listen_port,
-            cert_option: self.cert_option
+            cert_option: self.cert_option.clone(),
+            tuning: default(),
+            limits: default(),
+            ip_filter: default()
         }) {
             panic!("{e}");
         }
@@ -90,7 +93,11 @@ fn main() {
             max_clients: 10,
             buf_size: 512,
             send_timeout_secs: 10,
-            recv_timeout_secs: Some(10)
+            recv_timeout_secs: Some(10),
+            queue: default(),
+            ping_secs: Some(1),
+            require_approval: false,
+            pending_timeout_secs: Some(10)
         }
     ))
     .add_plugins(SereverPlugin{
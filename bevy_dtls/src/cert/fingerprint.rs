@@ -0,0 +1,44 @@
+use anyhow::bail;
+use sha2::{Digest, Sha256};
+use webrtc_dtls::crypto::Certificate;
+
+pub type Fingerprint = [u8; 32];
+
+#[inline]
+pub fn fingerprint_der(der: &[u8]) -> Fingerprint {
+    Sha256::digest(der).into()
+}
+
+// sha-256 of the leaf certificate, 
+// publish this out of band for clients pinning self signed server
+pub fn fingerprint(cert: &Certificate) -> anyhow::Result<Fingerprint> {
+    let Some(leaf) = cert.certificate.first() else {
+        bail!("certificate is empty");
+    };
+
+    Ok(fingerprint_der(leaf.as_ref()))
+}
+
+// colon separated upper hex like AB:CD:...
+pub fn to_hex(fingerprint: &Fingerprint) -> String {
+    fingerprint.iter()
+    .map(|b| format!("{b:02X}"))
+    .collect::<Vec<String>>()
+    .join(":")
+}
+
+// accepts both colon separated and plain hex
+pub fn from_hex(hex: &str) -> anyhow::Result<Fingerprint> {
+    let digits: String = hex.chars()
+    .filter(|c| *c != ':')
+    .collect();
+    if digits.len() != 64 || !digits.is_ascii() {
+        bail!("fingerprint must be 32 bytes hex, got {} digits", digits.len());
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (i, b) in fingerprint.iter_mut().enumerate() {
+        *b = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(fingerprint)
+}
@@ -0,0 +1,185 @@
+use std::{
+    fs::{self, OpenOptions}, 
+    io::{self, Write}, 
+    path::Path
+};
+use rcgen::{
+    BasicConstraints, 
+    Certificate, 
+    CertificateParams, 
+    DnType, 
+    ExtendedKeyUsagePurpose, 
+    IsCa, 
+    KeyPair, 
+    KeyUsagePurpose
+};
+
+// pem layout consumed by cert::loader, 
+// {name}.priv.pem for key and {name}.pub.pem for certificate chain
+#[derive(Clone, Debug)]
+pub struct PemPair {
+    pub priv_pem: String,
+    pub pub_pem: String
+}
+
+impl PemPair {
+    pub fn write(&self, dir: impl AsRef<Path>, name: &str) -> anyhow::Result<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+        write_private(&dir.join(format!("{name}.priv.pem")), &self.priv_pem)?;
+        fs::write(dir.join(format!("{name}.pub.pem")), &self.pub_pem)?;
+        Ok(())
+    }
+
+    pub fn read(dir: impl AsRef<Path>, name: &str) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        Ok(Self{
+            priv_pem: fs::read_to_string(dir.join(format!("{name}.priv.pem")))?,
+            pub_pem: fs::read_to_string(dir.join(format!("{name}.pub.pem")))?
+        })
+    }
+}
+
+// private keys are readable only by owner on unix
+pub(crate) fn write_private(path: &Path, contents: &str) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true)
+    .create(true)
+    .truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    // mode is applied only on creation, existing file is narrowed here
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents.as_bytes())
+}
+
+pub fn generate_self_signed(subject_alt_names: Vec<String>) 
+-> anyhow::Result<PemPair> {
+    let key = KeyPair::generate()?;
+    let cert = CertificateParams::new(subject_alt_names)?
+    .self_signed(&key)?;
+
+    Ok(PemPair{
+        priv_pem: key.serialize_pem(),
+        pub_pem: cert.pem()
+    })
+}
+
+// reads cached self signed certificate if both files exist,
+// otherwise generates new one and caches it to the paths
+pub fn load_or_generate_self_signed(
+    subject_alt_names: Vec<String>,
+    priv_key_path: impl AsRef<Path>,
+    certificate_path: impl AsRef<Path>
+) -> anyhow::Result<PemPair> {
+    let priv_key_path = priv_key_path.as_ref();
+    let certificate_path = certificate_path.as_ref();
+
+    if priv_key_path.exists() && certificate_path.exists() {
+        return Ok(PemPair{
+            priv_pem: fs::read_to_string(priv_key_path)?,
+            pub_pem: fs::read_to_string(certificate_path)?
+        });
+    }
+
+    let pem_pair = generate_self_signed(subject_alt_names)?;
+    for path in [priv_key_path, certificate_path] {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+    }
+    write_private(priv_key_path, &pem_pair.priv_pem)?;
+    fs::write(certificate_path, &pem_pair.pub_pem)?;
+    Ok(pem_pair)
+}
+
+pub struct LocalCa {
+    key: KeyPair,
+    cert: Certificate,
+    pub_pem: String
+}
+
+impl LocalCa {
+    pub fn generate(common_name: &str) -> anyhow::Result<Self> {
+        let key = KeyPair::generate()?;
+        let mut params = CertificateParams::new(Vec::<String>::new())?;
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, common_name);
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature
+        ];
+        let cert = params.self_signed(&key)?;
+        let pub_pem = cert.pem();
+
+        Ok(Self{
+            key,
+            cert,
+            pub_pem
+        })
+    }
+
+    pub fn from_pem(pem_pair: &PemPair) -> anyhow::Result<Self> {
+        let key = KeyPair::from_pem(&pem_pair.priv_pem)?;
+        // re-signed only to be used as issuer, 
+        // subject and key stay same as the original
+        let cert = CertificateParams::from_ca_cert_pem(&pem_pair.pub_pem)?
+        .self_signed(&key)?;
+
+        Ok(Self{
+            key,
+            cert,
+            pub_pem: pem_pair.pub_pem.clone()
+        })
+    }
+
+    #[inline]
+    pub fn to_pem(&self) -> PemPair {
+        PemPair{
+            priv_pem: self.key.serialize_pem(),
+            pub_pem: self.pub_pem.clone()
+        }
+    }
+
+    // chain contains ca certificate after the leaf,
+    // so that the file also works as root ca for peers
+    pub fn issue_server(&self, subject_alt_names: Vec<String>) 
+    -> anyhow::Result<PemPair> {
+        let common_name = subject_alt_names.first()
+        .cloned()
+        .unwrap_or_default();
+        let mut params = CertificateParams::new(subject_alt_names)?;
+        params.distinguished_name.push(DnType::CommonName, common_name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ServerAuth];
+
+        self.issue(params)
+    }
+
+    pub fn issue_client(&self, common_name: &str) -> anyhow::Result<PemPair> {
+        let mut params = CertificateParams::new(Vec::<String>::new())?;
+        params.distinguished_name.push(DnType::CommonName, common_name);
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+
+        self.issue(params)
+    }
+
+    fn issue(&self, params: CertificateParams) -> anyhow::Result<PemPair> {
+        let key = KeyPair::generate()?;
+        let cert = params.signed_by(&key, &self.cert, &self.key)?;
+
+        Ok(PemPair{
+            priv_pem: key.serialize_pem(),
+            pub_pem: format!("{}{}", cert.pem(), self.pub_pem)
+        })
+    }
+}
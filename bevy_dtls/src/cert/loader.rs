@@ -1,37 +1,90 @@
 use std::{
-    fs::File, 
-    io::{BufReader, Read}, 
+    fs::File,
+    io::{BufReader, Read},
     path::PathBuf
 };
 use rcgen::KeyPair;
 use rustls::pki_types::CertificateDer;
 use webrtc_dtls::crypto::{Certificate, CryptoPrivateKey};
 
-pub(crate) fn load_key(path: PathBuf) 
+#[derive(Clone, Debug)]
+pub enum CertSource {
+    Path(PathBuf),
+    Pem(Vec<u8>),
+    Der(Vec<u8>)
+}
+
+impl From<&'static str> for CertSource {
+    #[inline]
+    fn from(path: &'static str) -> Self {
+        Self::Path(path.into())
+    }
+}
+
+impl CertSource {
+    pub fn to_key(&self) -> anyhow::Result<CryptoPrivateKey> {
+        match self {
+            CertSource::Path(path) => load_key(path.clone()),
+            CertSource::Pem(pem) => parse_key_pem(pem),
+            CertSource::Der(der) => parse_key_der(der)
+        }
+    }
+
+    pub fn to_certificate(&self)
+    -> anyhow::Result<Vec<CertificateDer<'static>>> {
+        match self {
+            CertSource::Path(path) => load_certtificate(path.clone()),
+            CertSource::Pem(pem) => parse_certificate_pem(pem),
+            CertSource::Der(der) => Ok(vec![CertificateDer::from(der.clone())])
+        }
+    }
+}
+
+pub fn parse_key_pem(pem: &[u8]) -> anyhow::Result<CryptoPrivateKey> {
+    let txt = std::str::from_utf8(pem)?;
+    let key_pair = KeyPair::from_pem(txt)?;
+    let priv_key = CryptoPrivateKey::from_key_pair(&key_pair)?;
+    Ok(priv_key)
+}
+
+pub fn parse_key_der(der: &[u8]) -> anyhow::Result<CryptoPrivateKey> {
+    let key_pair = KeyPair::try_from(der)?;
+    let priv_key = CryptoPrivateKey::from_key_pair(&key_pair)?;
+    Ok(priv_key)
+}
+
+pub fn parse_certificate_pem(pem: &[u8])
+-> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(pem);
+
+    let cert = rustls_pemfile::certs(&mut reader)
+    .collect::<Result<Vec<CertificateDer<'static>>, _>>()?;
+    // fails here rather than on handshake
+    anyhow::ensure!(!cert.is_empty(), "no certificate is found in pem");
+    Ok(cert)
+}
+
+pub fn load_key(path: PathBuf)
 -> anyhow::Result<CryptoPrivateKey> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
-    
+
     let mut buf = vec![];
     reader.read_to_end(&mut buf)?;
-    let txt = String::from_utf8(buf)?;
-
-    let key_pair = KeyPair::from_pem(txt.as_str())?;
-    let priv_key = CryptoPrivateKey::from_key_pair(&key_pair)?;
-    Ok(priv_key)
-} 
+    parse_key_pem(&buf)
+}
 
-pub(crate) fn load_certtificate(path: PathBuf)
+pub fn load_certtificate(path: PathBuf)
 -> anyhow::Result<Vec<CertificateDer<'static>>> {
     let file = File::open(path)?;
     let mut reader = BufReader::new(file);
 
-    let cert = rustls_pemfile::certs(&mut reader)
-    .collect::<Result<Vec<CertificateDer<'static>>, _>>()?;
-    Ok(cert)
+    let mut buf = vec![];
+    reader.read_to_end(&mut buf)?;
+    parse_certificate_pem(&buf)
 }
 
-pub(crate) fn load_key_and_certificate(
+pub fn load_key_and_certificate(
     priv_key_path: PathBuf,
     certificate_path: PathBuf
 ) -> anyhow::Result<Certificate> {
@@ -43,3 +96,16 @@ pub(crate) fn load_key_and_certificate(
         private_key
     })
 }
+
+pub fn key_and_certificate_from(
+    priv_key: &CertSource,
+    certificate: &CertSource
+) -> anyhow::Result<Certificate> {
+    let private_key = priv_key.to_key()?;
+    let certificate = certificate.to_certificate()?;
+
+    Ok(Certificate{
+        certificate,
+        private_key
+    })
+}
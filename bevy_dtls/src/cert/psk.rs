@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use webrtc_dtls::cipher_suite::CipherSuiteId;
+
+// resolves psk identity sent by client to the key at handshake time
+pub type PskLookup = Arc<dyn Fn(&[u8]) -> anyhow::Result<Vec<u8>> + Send + Sync>;
+
+// psk callback of webrtc dtls config
+pub(crate) type DtlsPskCallback = Arc<
+    dyn Fn(&[u8]) -> Result<Vec<u8>, webrtc_dtls::Error> + Send + Sync
+>;
+
+#[inline]
+pub(crate) fn psk_cipher_suites() -> Vec<CipherSuiteId> {
+    vec![
+        CipherSuiteId::Tls_Psk_With_Aes_128_Gcm_Sha256,
+        CipherSuiteId::Tls_Psk_With_Aes_128_Ccm,
+        CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8
+    ]
+}
+
+#[inline]
+pub(crate) fn to_dtls_psk_callback(lookup: PskLookup) -> DtlsPskCallback {
+    Arc::new(move |identity: &[u8]| {
+        lookup(identity)
+        .map_err(|e| webrtc_dtls::Error::Other(e.to_string()))
+    })
+}
@@ -1,10 +1,19 @@
-use rustls::RootCertStore;
+use std::sync::Arc;
+use rustls::{pki_types::CertificateDer, RootCertStore};
 use webrtc_dtls::config::{Config, ExtendedMasterSecretType};
-use crate::cert::loader;
+use crate::cert::{
+    fingerprint::{self, Fingerprint}, 
+    loader::{self, CertSource}, 
+    psk::{self, PskLookup}
+};
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum ClientCertOption {
     Insecure,
+    // accepts server only if sha-256 of its leaf certificate matches
+    Pinned {
+        fingerprint: Fingerprint
+    },
     Load {
         server_name: &'static str,
         root_ca_path: &'static str
@@ -14,6 +23,20 @@ pub enum ClientCertOption {
         priv_key_path: &'static str,
         certificate_path: &'static str,
         root_ca_path: &'static str
+    },
+    LoadFrom {
+        server_name: String,
+        root_ca: CertSource
+    },
+    LoadFromWithClientAuth {
+        server_name: String,
+        priv_key: CertSource,
+        certificate: CertSource,
+        root_ca: CertSource
+    },
+    Psk {
+        identity: Vec<u8>,
+        key: Vec<u8>
     }
 }
 
@@ -27,6 +50,32 @@ impl ClientCertOption {
                     ..Default::default()
                 }
             }
+            ClientCertOption::Pinned { fingerprint: pinned } => {
+                Config{
+                    // chain verification is skipped, 
+                    // verify_peer_certificate is still called
+                    insecure_skip_verify: true,
+                    verify_peer_certificate: Some(Arc::new(
+                        move |raw_certs: &[Vec<u8>], _: &[CertificateDer<'static>]| {
+                            let Some(leaf) = raw_certs.first() else {
+                                return Err(webrtc_dtls::Error::Other(
+                                    "server has no certificate".to_string()
+                                ));
+                            };
+
+                            if fingerprint::fingerprint_der(leaf) != pinned {
+                                return Err(webrtc_dtls::Error::Other(format!(
+                                    "server certificate fingerprint {} does not match",
+                                    fingerprint::to_hex(&fingerprint::fingerprint_der(leaf))
+                                )));
+                            }
+                            Ok(())
+                        }
+                    )),
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    ..Default::default()
+                }
+            }
             ClientCertOption::Load { server_name, root_ca_path } => {
                 let mut root_ca_store = RootCertStore::empty();
                 let root_ca = loader::load_certtificate(root_ca_path.into())?;
@@ -66,6 +115,56 @@ impl ClientCertOption {
                     ..Default::default()
                 }
             }
+            ClientCertOption::LoadFrom { server_name, root_ca } => {
+                let mut root_ca_store = RootCertStore::empty();
+                for c in root_ca.to_certificate()? {
+                    root_ca_store.add(c)?;
+                }
+
+                Config{
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    roots_cas: root_ca_store,
+                    server_name,
+                    ..Default::default()
+                }
+            }
+            ClientCertOption::LoadFromWithClientAuth { 
+                server_name, 
+                priv_key, 
+                certificate, 
+                root_ca 
+            } => {
+                let cert = loader::key_and_certificate_from(
+                    &priv_key, 
+                    &certificate
+                )?;
+
+                let mut root_ca_store = RootCertStore::empty();
+                for c in root_ca.to_certificate()? {
+                    root_ca_store.add(c)?;
+                }
+
+                Config{
+                    certificates: vec![cert],
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    roots_cas: root_ca_store,
+                    server_name,
+                    ..Default::default()
+                }
+            }
+            ClientCertOption::Psk { identity, key } => {
+                let psk_lookup: PskLookup = Arc::new(
+                    move |_: &[u8]| -> anyhow::Result<Vec<u8>> { Ok(key.clone()) }
+                );
+
+                Config{
+                    psk: Some(psk::to_dtls_psk_callback(psk_lookup)),
+                    psk_identity_hint: Some(identity),
+                    cipher_suites: psk::psk_cipher_suites(),
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    ..Default::default()
+                }
+            }
         };
 
         Ok(config)
@@ -1,9 +1,13 @@
 use std::{net::IpAddr, sync::Arc, time::Duration};
-use anyhow::{anyhow, bail};
 use bevy::{
     prelude::*, 
     tasks::futures_lite::future
 };
+use async_channel::{
+    Receiver as QueueRx, 
+    Sender as QueueTx, 
+    TryRecvError as QueueTryRecvError
+};
 use bytes::{Bytes, BytesMut};
 use tokio::{
     net::UdpSocket as TokioUdpSocket, 
@@ -16,10 +20,19 @@ use tokio::{
         error::TryRecvError
     }, 
     task::JoinHandle,
-    time::timeout
+    time::{timeout, sleep}
 };
 use webrtc_dtls::conn::DTLSConn;
 use webrtc_util::Conn;
+use crate::{
+    disconnect::DisconnectReason, 
+    error::DtlsError, 
+    frame::{self, Frame}, 
+    ping, 
+    queue::{self, DropCounter, DtlsQueueConfig}, 
+    stats::{DtlsConnStats, StatsCounter}, 
+    tuning::DtlsTuning
+};
 use super::cert_option::ClientCertOption;
 
 #[derive(Clone)]
@@ -28,12 +41,13 @@ pub struct DtlsClientConfig {
     pub server_port: u16,
     pub client_addr: IpAddr,
     pub client_port: u16,
-    pub cert_option: ClientCertOption
+    pub cert_option: ClientCertOption,
+    pub tuning: DtlsTuning
 }
 
 impl DtlsClientConfig {
     async fn connect(self, timeout_secs: u64) 
-    -> anyhow::Result<Arc<impl Conn + Sync + Send>> {
+    -> Result<Arc<dyn Conn + Sync + Send>, DtlsError> {
         debug!("connecting to {}", self.server_addr);
         timeout(
             Duration::from_secs(timeout_secs),
@@ -46,29 +60,61 @@ impl DtlsClientConfig {
                 socket.connect((self.server_addr, self.server_port))
                 .await?;
 
+                let mut dtls_config = self.cert_option.to_dtls_config()
+                .map_err(DtlsError::cert)?;
+                self.tuning.apply(&mut dtls_config);
+
                 let dtls_conn = DTLSConn::new(
                     Arc::new(socket), 
-                    self.cert_option.to_dtls_config()?, 
+                    dtls_config, 
                     true, 
                     None
                 )
-                .await?;
+                .await
+                .map_err(|e| DtlsError::HandshakeFailed(e.to_string()))?;
         
-                Ok(Arc::new(dtls_conn))
+                let conn: Arc<dyn Conn + Sync + Send> = Arc::new(dtls_conn);
+                Ok(conn)
             }
         )
         .await?
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DtlsClientState {
+    Closed,
+    Connecting,
+    Connected,
+    // disconnect is called or conn is closed, 
+    // but loops are not finished yet
+    Disconnecting
+}
+
+pub enum DtlsClientConnect {
+    Connecting,
+    Connected,
+    Failed(DtlsError)
+}
+
 pub struct DtlsClientHealth {
-    pub sender: Option<anyhow::Result<()>>,
-    pub recver: Option<anyhow::Result<()>>,
-    pub closed: bool
+    pub sender: Option<Result<(), DtlsError>>,
+    pub recver: Option<Result<(), DtlsError>>,
+    pub closed: bool,
+    // set when closed, sent by server or passed to disconnect
+    pub reason: Option<DisconnectReason>
+}
+
+pub struct DtlsClientQueueFull {
+    // total dropped messages of current conn
+    pub dropped: u64,
+    // disconnect backpressure is triggered
+    pub disconnect: bool
 }
 
 pub enum DtlsClientTimeout {
-    Send(Bytes)
+    Send(Bytes),
+    Recv
 }
 
 struct DtlsClientClose;
@@ -76,27 +122,34 @@ struct DtlsClientClose;
 struct DtlsClientSender {
     conn: Arc<dyn Conn + Sync + Send>,
     timeout_secs: u64,
-    send_rx: TokioRx<Bytes>,
+    heartbeat_secs: Option<u64>,
+    ping_secs: Option<u64>,
+    stats: Arc<StatsCounter>,
+    send_rx: QueueRx<Bytes>,
     timeout_tx: TokioTx<DtlsClientTimeout>,
     close_rx: TokioRx<DtlsClientClose>
 }
 
 impl DtlsClientSender {
     #[inline]
-    fn new(conn: Arc<dyn Conn + Send + Sync>, timeout_secs: u64)
-    -> (
-        TokioTx<Bytes>, 
-        TokioRx<DtlsClientTimeout>, 
-        TokioTx<DtlsClientClose>, 
-        Self
-    ) {
-        let (send_tx, send_rx) = tokio_channel::<Bytes>();
-        let (timeout_tx, timeout_rx) = tokio_channel::<DtlsClientTimeout>();
+    fn new(
+        conn: Arc<dyn Conn + Send + Sync>, 
+        timeout_secs: u64,
+        heartbeat_secs: Option<u64>,
+        ping_secs: Option<u64>,
+        send_capacity: usize,
+        stats: Arc<StatsCounter>,
+        timeout_tx: TokioTx<DtlsClientTimeout>
+    ) -> (QueueTx<Bytes>, TokioTx<DtlsClientClose>, Self) {
+        let (send_tx, send_rx) = queue::channel::<Bytes>(send_capacity);
         let(close_tx, close_rx) = tokio_channel::<DtlsClientClose>();
     
-        (send_tx, timeout_rx, close_tx, Self{
+        (send_tx, close_tx, Self{
             conn,
             timeout_secs,
+            heartbeat_secs,
+            ping_secs,
+            stats,
             send_rx,
             timeout_tx,
             close_rx,
@@ -108,33 +161,78 @@ impl DtlsClientSender {
         Duration::from_secs(self.timeout_secs)
     }
 
-    async fn send_loop(mut self)-> anyhow::Result<()> {
+    #[inline]
+    fn heartbeat_secs(&self) -> Duration {
+        match self.heartbeat_secs {
+            Some(t) => Duration::from_secs(t),
+            None => Duration::MAX
+        }
+    }
+
+    async fn send_loop(mut self)-> Result<(), DtlsError> {
+        let heartbeat_dur = self.heartbeat_secs();
+        let mut ping_interval = ping::ping_interval(self.ping_secs);
+
         let result = loop {
             select! {
                 biased;
 
                 Some(_) = self.close_rx.recv() => break Ok(()),
-                Some(msg) = self.send_rx.recv() => {
+                Ok(msg) = self.send_rx.recv() => {
                     match timeout(
                         self.timeout_secs(), 
-                        self.conn.send(&msg)
+                        self.conn.send(&frame::data(&msg))
                     )
                     .await {
                         Ok(r) => {
                             match r {
-                                Ok(n) => trace!("sent {n} bytes"),
-                                Err(e) => break Err(anyhow!(e))
+                                Ok(n) => {
+                                    self.stats.on_sent(n);
+                                    trace!("sent {n} bytes");
+                                }
+                                Err(e) => break Err(DtlsError::from(e))
                             }
                         }
                         Err(_) => {
+                            self.stats.on_send_timeout();
                             if let Err(e) = self.timeout_tx.send(
                                 DtlsClientTimeout::Send(msg)
                             ) {
-                                break Err(anyhow!(e));
+                                break Err(DtlsError::from(e));
                             }
                         }
                     }
                 }
+                () = ping::tick(&mut ping_interval), if ping_interval.is_some() => {
+                    let seq = self.stats.ping.on_ping();
+                    match timeout(
+                        self.timeout_secs(), 
+                        self.conn.send(&frame::ping(seq))
+                    )
+                    .await {
+                        Ok(Ok(n)) => {
+                            self.stats.on_sent(n);
+                            trace!("sent ping {seq}");
+                        }
+                        Ok(Err(e)) => break Err(DtlsError::from(e)),
+                        Err(_) => debug!("sending ping timed out")
+                    }
+                }
+                // sleep is reset by every message, so sent only while idle
+                () = sleep(heartbeat_dur) => {
+                    match timeout(
+                        self.timeout_secs(), 
+                        self.conn.send(&frame::heartbeat())
+                    )
+                    .await {
+                        Ok(Ok(n)) => {
+                            self.stats.on_sent(n);
+                            trace!("sent heartbeat");
+                        }
+                        Ok(Err(e)) => break Err(DtlsError::from(e)),
+                        Err(_) => debug!("sending heartbeat timed out")
+                    }
+                }
                 else => {
                     warn!(
                         "is dtls client dropped before disconnection? \
@@ -154,56 +252,123 @@ impl DtlsClientSender {
 struct DtlsClientRecver {
     conn: Arc<dyn Conn + Sync + Send>,
     buf_size: usize,
-    recv_tx: TokioTx<Bytes>,
+    timeout_secs: Option<u64>,
+    // pong is sent by recv loop, so it never waits longer than this
+    send_timeout_secs: u64,
+    queue_config: DtlsQueueConfig,
+    drop_counter: Arc<DropCounter>,
+    stats: Arc<StatsCounter>,
+    recv_tx: QueueTx<Bytes>,
+    timeout_tx: TokioTx<DtlsClientTimeout>,
     close_rx: TokioRx<DtlsClientClose>
 }
 
 impl DtlsClientRecver {
     #[inline]
-    fn new(conn: Arc<dyn Conn + Sync + Send>, buf_size: usize)
-    -> (TokioRx<Bytes>, TokioTx<DtlsClientClose>, Self) {
-        let (recv_tx, recv_rx) = tokio_channel::<Bytes>();
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        conn: Arc<dyn Conn + Sync + Send>, 
+        buf_size: usize,
+        timeout_secs: Option<u64>,
+        send_timeout_secs: u64,
+        queue_config: DtlsQueueConfig,
+        drop_counter: Arc<DropCounter>,
+        stats: Arc<StatsCounter>,
+        timeout_tx: TokioTx<DtlsClientTimeout>
+    ) -> (QueueRx<Bytes>, TokioTx<DtlsClientClose>, Self) {
+        let (recv_tx, recv_rx) = queue::channel::<Bytes>(queue_config.recv_capacity);
         let (close_tx, close_rx) = tokio_channel::<DtlsClientClose>();
 
         (recv_rx, close_tx, Self{
             conn,
             buf_size,
+            timeout_secs,
+            send_timeout_secs,
+            queue_config,
+            drop_counter,
+            stats,
             recv_tx,
+            timeout_tx,
             close_rx,
         })
     }
 
-    async fn recv_loop(mut self) -> anyhow::Result<()> {
+    #[inline]
+    fn timeout_secs(&self) -> Duration {
+        match self.timeout_secs {
+            Some(t) => Duration::from_secs(t),
+            None => Duration::MAX
+        }
+    }
+
+    async fn recv_loop(mut self) 
+    -> Result<Option<DisconnectReason>, DtlsError> {
         let mut buf = BytesMut::zeroed(self.buf_size);
+        let timeout_dur = self.timeout_secs();
 
         let result = loop {
             let n = select! {
                 biased;
 
-                Some(_) = self.close_rx.recv() => break Ok(()),
+                Some(_) = self.close_rx.recv() => break Ok(None),
                 r = self.conn.recv(&mut buf) => {
                     match r {
                         Ok(n) => n,
-                        Err(e) => break Err(anyhow!(e))
+                        Err(e) => break Err(DtlsError::from(e))
+                    }
+                }
+                () = sleep(timeout_dur) => {
+                    self.stats.on_recv_timeout();
+                    if let Err(e) = self.timeout_tx.send(DtlsClientTimeout::Recv) {
+                        break Err(DtlsError::from(e));
                     }
+                    continue;
                 }
                 else => {
                     warn!(
                         "is dtls client dropped before disconnection? \
                         recver loop is closing anyway"
                     );
-                    break Ok(());
+                    break Ok(None);
                 }
             };
 
             let receved = buf.split_to(n)
             .freeze();
-            if let Err(e) = self.recv_tx.send(receved) {
-                break Err(anyhow!(e));
-            }
-
             buf.resize(self.buf_size, 0);
+            self.stats.on_received(n);
             trace!("received {n}bytes");
+
+            match frame::decode(receved) {
+                Some(Frame::Data(payload)) => {
+                    if let Err(e) = queue::push(
+                        &self.recv_tx, 
+                        payload, 
+                        self.queue_config.backpressure, 
+                        &self.drop_counter
+                    ) {
+                        break Err(e);
+                    }
+                }
+                Some(Frame::Disconnect(reason)) => {
+                    debug!("server is disconnecting: {reason:?}");
+                    break Ok(Some(reason));
+                }
+                Some(Frame::Heartbeat) => trace!("heartbeat from server"),
+                Some(Frame::Ping(seq)) => {
+                    match timeout(
+                        Duration::from_secs(self.send_timeout_secs), 
+                        self.conn.send(&frame::pong(seq))
+                    )
+                    .await {
+                        Ok(Ok(n)) => self.stats.on_sent(n),
+                        Ok(Err(e)) => break Err(DtlsError::from(e)),
+                        Err(_) => debug!("sending pong timed out")
+                    }
+                }
+                Some(Frame::Pong(seq)) => self.stats.ping.on_pong(seq),
+                None => warn!("dropping unknown datagram from server")
+            }
         };
 
         self.conn.close().await?;
@@ -218,23 +383,44 @@ pub struct DtlsClient {
 
     conn: Option<Arc<dyn Conn + Sync + Send>>,
     is_running: bool,
+    disconnect_reason: Option<DisconnectReason>,
+
+    config: Option<DtlsClientConfig>,
+    connect_handle: Option<JoinHandle<Result<Arc<dyn Conn + Sync + Send>, DtlsError>>>,
+    connect_notified: bool,
 
     send_timeout_secs: u64,
-    send_handle: Option<JoinHandle<anyhow::Result<()>>>,
-    send_tx: Option<TokioTx<Bytes>>,
-    send_timeout_rx: Option<TokioRx<DtlsClientTimeout>>,
+    heartbeat_secs: Option<u64>,
+    ping_secs: Option<u64>,
+    send_handle: Option<JoinHandle<Result<(), DtlsError>>>,
+    send_tx: Option<QueueTx<Bytes>>,
     close_send_tx: Option<TokioTx<DtlsClientClose>>,
 
-    recv_handle: Option<JoinHandle<anyhow::Result<()>>>,
+    recv_handle: Option<JoinHandle<Result<Option<DisconnectReason>, DtlsError>>>,
     recv_buf_size: usize,
-    recv_rx: Option<TokioRx<Bytes>>,
-    close_recv_tx: Option<TokioTx<DtlsClientClose>>
+    recv_timeout_secs: Option<u64>,
+    recv_rx: Option<QueueRx<Bytes>>,
+    close_recv_tx: Option<TokioTx<DtlsClientClose>>,
+
+    queue_config: DtlsQueueConfig,
+    drop_counter: Arc<DropCounter>,
+    dropped_reported: u64,
+    stats: Arc<StatsCounter>,
+
+    timeout_tx: Option<TokioTx<DtlsClientTimeout>>,
+    timeout_rx: Option<TokioRx<DtlsClientTimeout>>
 }
 
 impl DtlsClient {
     #[inline]
-    pub fn new(recv_buf_size: usize, send_timeout_secs: u64) 
-    -> anyhow::Result<Self> {
+    pub fn new(
+        recv_buf_size: usize, 
+        send_timeout_secs: u64,
+        recv_timeout_secs: Option<u64>,
+        heartbeat_secs: Option<u64>,
+        queue_config: DtlsQueueConfig,
+        ping_secs: Option<u64>
+    ) -> Result<Self, DtlsError> {
         let rt = runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?; 
@@ -244,17 +430,32 @@ impl DtlsClient {
 
             conn: None,
             is_running: false,
+            disconnect_reason: None,
+
+            config: None,
+            connect_handle: None,
+            connect_notified: false,
 
             send_timeout_secs,
+            heartbeat_secs,
+            ping_secs,
             send_handle: None,
             send_tx: None,
-            send_timeout_rx: None,
             close_send_tx: None,
             
             recv_handle: None,
             recv_buf_size,
+            recv_timeout_secs,
             recv_rx: None,
-            close_recv_tx: None
+            close_recv_tx: None,
+
+            queue_config,
+            drop_counter: default(),
+            dropped_reported: 0,
+            stats: default(),
+
+            timeout_tx: None,
+            timeout_rx: None
         })
     }
 
@@ -263,49 +464,123 @@ impl DtlsClient {
         // set closed by health check
         !self.is_running 
         && self.conn.is_none() 
+        && self.connect_handle.is_none()
         && self.recv_handle.is_none()
         && self.send_handle.is_none()
 
         // set closed by calling disconnect
         && self.send_tx.is_none()
-        && self.send_timeout_rx.is_none()
+        && self.timeout_tx.is_none()
+        && self.timeout_rx.is_none()
         && self.close_send_tx.is_none()
         && self.recv_rx.is_none()
         && self.close_recv_tx.is_none()
     }
 
+    pub fn state(&self) -> DtlsClientState {
+        if self.connect_handle.is_some() {
+            DtlsClientState::Connecting
+        } else if self.close_send_tx.is_some() 
+        && self.close_recv_tx.is_some() {
+            DtlsClientState::Connected
+        } else if self.is_closed() {
+            DtlsClientState::Closed
+        } else {
+            DtlsClientState::Disconnecting
+        }
+    }
+
+    // handshake runs on runtime,
+    // loops are started by connect_check when it is done
     #[inline]
     pub fn start(&mut self, config: DtlsClientConfig) 
-    -> anyhow::Result<()> {
+    -> Result<(), DtlsError> {
         if !self.is_closed() {
-            bail!("dtls client is not closed");
+            return Err(DtlsError::InvalidState("dtls client is not closed"));
         }
 
-        self.start_connect(config)?;
-        self.start_send_loop()?;
-        self.start_recv_loop()
+        self.start_connect(config);
+        Ok(())
+    }
+
+    // config is forgotten by explicit disconnect
+    #[inline]
+    pub fn can_reconnect(&self) -> bool {
+        self.config.is_some()
     }
 
-    pub fn send(&self, message: Bytes) -> anyhow::Result<()> {
-        let Some(ref send_tx) = self.send_tx else {
-            bail!("conn is not started or is disconnected: send tx is None");
+    // starts again with config passed to last start
+    pub fn reconnect(&mut self) -> Result<(), DtlsError> {
+        let Some(config) = self.config.clone() else {
+            return Err(DtlsError::InvalidState(
+                "dtls client has never started or is disconnected explicitly"
+            ));
         };
 
-        if let Err(e) = send_tx.send(message) {
-            bail!("conn is not started or is disconnected: {e}");
-        }
-        Ok(())
+        self.start(config)
     }
 
-    pub fn recv(&mut self) -> Option<Bytes> {
-        let Some(ref mut recv_rx) = self.recv_rx else {
+    pub fn connect_check(&mut self) -> Option<DtlsClientConnect> {
+        let handle_ref = self.connect_handle.as_ref()?;
+
+        // always notified before result even if handshake is already done
+        if !self.connect_notified {
+            self.connect_notified = true;
+            return Some(DtlsClientConnect::Connecting);
+        }
+        if !handle_ref.is_finished() {
             return None;
+        }
+
+        let handle = self.connect_handle.take()
+        .unwrap();
+        let conn = match future::block_on(handle) {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => return Some(DtlsClientConnect::Failed(e)),
+            Err(e) => return Some(DtlsClientConnect::Failed(DtlsError::from(e)))
         };
+        self.conn = Some(conn);
+        debug!("dtls client has connected");
+
+        let (timeout_tx, timeout_rx) = tokio_channel::<DtlsClientTimeout>();
+        self.timeout_tx = Some(timeout_tx);
+        self.timeout_rx = Some(timeout_rx);
+
+        // counted per conn
+        self.drop_counter = default();
+        self.dropped_reported = 0;
+        self.stats = default();
+
+        if let Err(e) = self.start_send_loop() {
+            return Some(DtlsClientConnect::Failed(e));
+        }
+        if let Err(e) = self.start_recv_loop() {
+            return Some(DtlsClientConnect::Failed(e));
+        }
+        Some(DtlsClientConnect::Connected)
+    }
+
+    pub fn send(&self, message: Bytes) -> Result<(), DtlsError> {
+        // not started or is disconnected
+        let Some(ref send_tx) = self.send_tx else {
+            return Err(DtlsError::ConnClosed);
+        };
+
+        queue::push(
+            send_tx, 
+            message, 
+            self.queue_config.backpressure, 
+            &self.drop_counter
+        )
+    }
+
+    pub fn recv(&mut self) -> Option<Bytes> {
+        let recv_rx = self.recv_rx.as_mut()?;
 
         match recv_rx.try_recv() {
             Ok(b) => Some(b),
             Err(e) => {
-                if matches!(e, TryRecvError::Disconnected) {
+                if matches!(e, QueueTryRecvError::Closed) {
                     debug!("recver loop looks closed before disconnection: {e}");
                 }
                 None
@@ -315,7 +590,7 @@ impl DtlsClient {
 
     pub fn timeout_check(&mut self) 
     -> std::result::Result<(), DtlsClientTimeout> {
-        let Some(ref mut timeout_rx) = self.send_timeout_rx else {
+        let Some(ref mut timeout_rx) = self.timeout_rx else {
             return Ok(());
         };
 
@@ -330,6 +605,32 @@ impl DtlsClient {
         }
     }
 
+    #[inline]
+    pub fn dropped_packets(&self) -> u64 {
+        self.drop_counter.dropped()
+    }
+
+    // stats of current or last conn
+    #[inline]
+    pub fn stats(&self) -> DtlsConnStats {
+        self.stats.snapshot(self.drop_counter.dropped())
+    }
+
+    // reports if messages are dropped since last check
+    pub fn queue_check(&mut self) -> Option<DtlsClientQueueFull> {
+        let dropped = self.drop_counter.dropped();
+        let disconnect = self.drop_counter.take_overflowed();
+        if dropped == self.dropped_reported && !disconnect {
+            return None;
+        }
+
+        self.dropped_reported = dropped;
+        Some(DtlsClientQueueFull{
+            dropped,
+            disconnect
+        })
+    }
+
     #[inline]
     pub fn health_check(&mut self) -> DtlsClientHealth {
         let sender_health = self.health_check_send_loop();
@@ -338,54 +639,107 @@ impl DtlsClient {
         && self.send_handle.is_none()
         && self.recv_handle.is_none();
 
-        if closed {
+        let reason = if closed {
             self.conn = None;
             self.is_running = false;
-        }
+            self.disconnect_reason.take()
+        } else {
+            None
+        };
         
         DtlsClientHealth{
             sender: sender_health,
             recver: recver_health,
-            closed
+            closed,
+            reason
         }
     }
 
     #[inline]
     pub fn disconnect(&mut self) {
-        self.close_send_loop();
-        self.close_recv_loop();
+        self.disconnect_with_reason(DisconnectReason::Disconnected);
     }
 
-    fn start_connect(&mut self, config: DtlsClientConfig) 
-    -> anyhow::Result<()> {
-        let conn = future::block_on(self.runtime.spawn(
+    pub fn disconnect_with_reason(&mut self, reason: DisconnectReason) {
+        self.config = None;
+        if let Some(handle) = self.connect_handle.take() {
+            handle.abort();
+            debug!("connecting is aborted");
+        }
+
+        let close_send_tx = self.close_send_tx.take();
+        let close_recv_tx = self.close_recv_tx.take();
+        self.timeout_tx = None;
+        self.timeout_rx = None;
+        self.send_tx = None;
+        self.recv_rx = None;
+
+        let Some(ref conn) = self.conn else {
+            return;
+        };
+        if close_send_tx.is_none() && close_recv_tx.is_none() {
+            return;
+        }
+
+        self.disconnect_reason.get_or_insert(reason);
+        let conn = Arc::clone(conn);
+        let timeout_dur = Duration::from_secs(self.send_timeout_secs);
+
+        // reason has to reach server before loops send close notify
+        self.runtime.spawn(async move {
+            match timeout(timeout_dur, conn.send(&frame::disconnect(reason)))
+            .await {
+                Ok(Ok(_)) => trace!("sent disconnect reason"),
+                Ok(Err(e)) => debug!("could not send disconnect reason: {e}"),
+                Err(_) => debug!("sending disconnect reason timed out")
+            }
+
+            if let Some(tx) = close_send_tx {
+                if let Err(e) = tx.send(DtlsClientClose) {
+                    debug!("sender loop looks already closed: {e}");
+                }
+            }
+            if let Some(tx) = close_recv_tx {
+                if let Err(e) = tx.send(DtlsClientClose) {
+                    debug!("recver loop looks already closed: {e}");
+                }
+            }
+        });
+    }
+
+    fn start_connect(&mut self, config: DtlsClientConfig) {
+        self.config = Some(config.clone());
+        let handle = self.runtime.spawn(
             config.connect(self.send_timeout_secs)
-        ))??;
-        self.conn = Some(conn);
-        debug!("dtls client has connected");
-        Ok(())
+        );
+        self.connect_handle = Some(handle);
+        self.connect_notified = false;
     }
 
-    fn start_send_loop(&mut self) -> anyhow::Result<()> {
+    fn start_send_loop(&mut self) -> Result<(), DtlsError> {
         if self.send_handle.is_some() {
-            bail!("join handle already exists, or health_check is not called");
+            return Err(DtlsError::InvalidState(
+                "join handle already exists, or health_check is not called"
+            ));
         }
         
-        let (
-            send_tx, 
-            timeout_rx, 
-            close_tx, 
-            sender
-        ) = DtlsClientSender::new(
+        let (send_tx, close_tx, sender) = DtlsClientSender::new(
             match self.conn {
                 Some(ref c) => Arc::clone(c),
-                None => bail!("conn is none")
+                None => return Err(DtlsError::InvalidState("conn is none"))
             },
             self.send_timeout_secs,
+            self.heartbeat_secs,
+            self.ping_secs,
+            self.queue_config.send_capacity,
+            Arc::clone(&self.stats),
+            match self.timeout_tx {
+                Some(ref tx) => tx.clone(),
+                None => return Err(DtlsError::InvalidState("timeout tx is None"))
+            }
         );
 
         self.send_tx = Some(send_tx);
-        self.send_timeout_rx = Some(timeout_rx);
         self.close_send_tx = Some(close_tx);
 
         let handle = self.runtime.spawn(sender.send_loop());
@@ -397,7 +751,7 @@ impl DtlsClient {
     }
 
     fn health_check_send_loop(&mut self) 
-    -> Option<anyhow::Result<()>> {
+    -> Option<Result<(), DtlsError>> {
         let handle_ref = self.send_handle.as_ref()?;
 
         if !handle_ref.is_finished() {
@@ -407,34 +761,61 @@ impl DtlsClient {
         let handle = self.send_handle.take()
         .unwrap();
         match future::block_on(handle) {
-            Ok(r) => Some(r),
-            Err(e) => Some(Err(anyhow!(e)))
+            Ok(Ok(())) => Some(Ok(())),
+            Ok(Err(e)) => {
+                // conn is no longer usable, recver loop is closed too
+                self.close_loops();
+                Some(Err(e))
+            }
+            Err(e) => {
+                self.close_loops();
+                Some(Err(DtlsError::from(e)))
+            }
         }
     }
 
-    fn close_send_loop(&mut self) {
-        if let Some(ref close_send_tx) = self.close_send_tx {
+    // closes loops without sending reason, 
+    // used when conn is closed by peer or error
+    fn close_loops(&mut self) {
+        if let Some(close_send_tx) = self.close_send_tx.take() {
             if let Err(e) = close_send_tx.send(DtlsClientClose) {
                 debug!("sender loop looks already closed: {e}");
             }
         }
+        if let Some(close_recv_tx) = self.close_recv_tx.take() {
+            if let Err(e) = close_recv_tx.send(DtlsClientClose) {
+                debug!("recver loop looks already closed: {e}");
+            }
+        }
 
-        self.close_send_tx = None;
-        self.send_timeout_rx = None;
+        self.timeout_tx = None;
+        self.timeout_rx = None;
         self.send_tx = None;
+        self.recv_rx = None;
     }
 
-    fn start_recv_loop(&mut self) -> anyhow::Result<()> {
+    fn start_recv_loop(&mut self) -> Result<(), DtlsError> {
         if self.recv_handle.is_some() {
-            bail!("join handle already exists, or health_check is not called");
+            return Err(DtlsError::InvalidState(
+                "join handle already exists, or health_check is not called"
+            ));
         }
         
         let (recv_rx, close_tx, recver) = DtlsClientRecver::new(
             match self.conn {
                 Some(ref c) => Arc::clone(c),
-                None => bail!("dtls conn is None")
+                None => return Err(DtlsError::InvalidState("dtls conn is None"))
             },
-            self.recv_buf_size
+            self.recv_buf_size,
+            self.recv_timeout_secs,
+            self.send_timeout_secs,
+            self.queue_config,
+            Arc::clone(&self.drop_counter),
+            Arc::clone(&self.stats),
+            match self.timeout_tx {
+                Some(ref tx) => tx.clone(),
+                None => return Err(DtlsError::InvalidState("timeout tx is None"))
+            }
         );
         self.recv_rx = Some(recv_rx);
         self.close_recv_tx = Some(close_tx);
@@ -448,7 +829,7 @@ impl DtlsClient {
     }
 
     fn health_check_recv_loop(&mut self) 
-    -> Option<anyhow::Result<()>> {
+    -> Option<Result<(), DtlsError>> {
         let handle_ref = self.recv_handle.as_ref()?;
 
         if !handle_ref.is_finished() {
@@ -458,19 +839,21 @@ impl DtlsClient {
         let handle = self.recv_handle.take()
         .unwrap();
         match future::block_on(handle) {
-            Ok(r) => Some(r),
-            Err(e) => Some(Err(anyhow!(e)))
-        }
-    }
-
-    fn close_recv_loop(&mut self) {
-        if let Some(ref close_recv_tx) = self.close_recv_tx {
-            if let Err(e) = close_recv_tx.send(DtlsClientClose) {
-                debug!("recver loop loos already closed: {e}");
+            Ok(Ok(Some(reason))) => {
+                // server is leaving, sender loop is no longer needed
+                self.disconnect_reason.get_or_insert(reason);
+                self.close_loops();
+                Some(Ok(()))
+            }
+            Ok(Ok(None)) => Some(Ok(())),
+            Ok(Err(e)) => {
+                self.close_loops();
+                Some(Err(e))
+            }
+            Err(e) => {
+                self.close_loops();
+                Some(Err(DtlsError::from(e)))
             }
         }
-
-        self.close_recv_tx = None;
-        self.recv_rx = None;   
     }
 }
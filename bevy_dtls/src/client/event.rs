@@ -1,17 +1,60 @@
-use anyhow::anyhow;
 use bevy::prelude::*;
 use bytes::Bytes;
-use super::dtls_client::{DtlsClient, DtlsClientTimeout};
+use crate::{disconnect::DisconnectReason, error::DtlsError};
+use super::dtls_client::{DtlsClient, DtlsClientConnect, DtlsClientTimeout};
 
 #[derive(Event, Debug)]
 pub enum DtlsClientEvent {
+    Connecting,
+    Connected,
+    ConnectFailed {
+        err: DtlsError
+    },
     SendTimeout {
         bytes: Bytes
     },
+    RecvTimeout,
+    QueueFull {
+        // total dropped messages of current conn
+        dropped: u64
+    },
     Error {
-        err: anyhow::Error
+        err: DtlsError
+    },
+    ConnClosed {
+        reason: Option<DisconnectReason>
+    },
+    Reconnecting {
+        attempt: u32
     },
-    ConnClosed
+    ReconnectGaveUp {
+        attempts: u32
+    }
+}
+
+pub fn connect_event_system(
+    mut dtls_client: ResMut<DtlsClient>,
+    mut dtls_events: EventWriter<DtlsClientEvent>
+) {
+    loop {
+        let Some(c) = dtls_client.connect_check() else {
+            return;
+        };
+
+        match c {
+            DtlsClientConnect::Connecting => {
+                dtls_events.send(DtlsClientEvent::Connecting);
+            }
+            DtlsClientConnect::Connected => {
+                dtls_events.send(DtlsClientEvent::Connected);
+            }
+            DtlsClientConnect::Failed(err) => {
+                dtls_events.send(DtlsClientEvent::ConnectFailed { 
+                    err 
+                });
+            }
+        }
+    }
 }
 
 pub fn timeout_event_system(
@@ -29,10 +72,31 @@ pub fn timeout_event_system(
                     bytes
                 });
             }
+            DtlsClientTimeout::Recv => {
+                dtls_events.send(DtlsClientEvent::RecvTimeout);
+            }
         }
     }
 }
 
+pub fn queue_event_system(
+    mut dtls_client: ResMut<DtlsClient>,
+    mut dtls_events: EventWriter<DtlsClientEvent>
+) {
+    let Some(full) = dtls_client.queue_check() else {
+        return;
+    };
+
+    if full.disconnect {
+        warn!("dtls client overflowed its queue, disconnecting");
+        dtls_client.disconnect_with_reason(DisconnectReason::QueueFull);
+    }
+
+    dtls_events.send(DtlsClientEvent::QueueFull { 
+        dropped: full.dropped 
+    });
+}
+
 pub fn health_event_system(
     mut dtls_client: ResMut<DtlsClient>,
     mut dtls_events: EventWriter<DtlsClientEvent>
@@ -40,15 +104,17 @@ pub fn health_event_system(
     let health = dtls_client.health_check();
     if let Some(Err(e)) = health.sender {
         dtls_events.send(DtlsClientEvent::Error { 
-            err: anyhow!("error from sender: {e}")
+            err: e
         });
     }
     if let Some(Err(e)) = health.recver {
         dtls_events.send(DtlsClientEvent::Error { 
-            err: anyhow!("error from recver: {e}")
+            err: e
         });
     }
     if health.closed {
-        dtls_events.send(DtlsClientEvent::ConnClosed);
+        dtls_events.send(DtlsClientEvent::ConnClosed { 
+            reason: health.reason 
+        });
     }
 }
@@ -1,13 +1,23 @@
 use bevy::prelude::*;
 use rustls::crypto::aws_lc_rs;
+use crate::queue::DtlsQueueConfig;
 use super::{
     dtls_client::DtlsClient, 
-    event::{self, DtlsClientEvent}
+    event::{self, DtlsClientEvent}, 
+    reconnect::{self, DtlsClientReconnector, DtlsReconnectPolicy}
 };
 
 pub struct DtlsClientPlugin {
     pub timeout_secs: u64,
-    pub buf_size: usize
+    pub buf_size: usize,
+    pub recv_timeout_secs: Option<u64>,
+    // sends keepalive while idle so that server recv timeout won't fire
+    pub heartbeat_secs: Option<u64>,
+    pub queue: DtlsQueueConfig,
+    // measures rtt and loss, see DtlsClient::stats
+    pub ping_secs: Option<u64>,
+    // reconnects after unexpected close when this is some
+    pub reconnect: Option<DtlsReconnectPolicy>
 }
 
 impl Plugin for DtlsClientPlugin {
@@ -18,16 +28,36 @@ impl Plugin for DtlsClientPlugin {
             panic!("failed to set up crypto provider");
         }
 
-        let dtls_client = match DtlsClient::new(self.buf_size, self.timeout_secs) {
+        let dtls_client = match DtlsClient::new(
+            self.buf_size, 
+            self.timeout_secs,
+            self.recv_timeout_secs,
+            self.heartbeat_secs,
+            self.queue,
+            self.ping_secs
+        ) {
             Ok(c) => c,
             Err(e) => panic!("{e}")
         };
 
         app.insert_resource(dtls_client)
         .add_event::<DtlsClientEvent>()
+        .add_systems(PreUpdate, event::connect_event_system)
         .add_systems(PostUpdate, (
             event::health_event_system,
-            event::timeout_event_system
+            event::timeout_event_system,
+            event::queue_event_system
         ).chain());
+
+        if let Some(ref policy) = self.reconnect {
+            app.insert_resource(DtlsClientReconnector::new(policy.clone()))
+            .add_systems(PostUpdate, (
+                reconnect::reconnect_watch_system,
+                reconnect::reconnect_system
+            )
+                .chain()
+                .after(event::timeout_event_system)
+            );
+        }
     }
 }
@@ -0,0 +1,189 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use super::{
+    dtls_client::DtlsClient,
+    event::DtlsClientEvent
+};
+
+#[derive(Clone, Debug)]
+pub struct DtlsReconnectPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_secs: f32,
+    pub max_backoff_secs: f32,
+    // ratio of backoff randomly subtracted, 0.0 to 1.0
+    pub jitter: f32
+}
+
+impl Default for DtlsReconnectPolicy {
+    fn default() -> Self {
+        Self{
+            max_attempts: 5,
+            initial_backoff_secs: 0.5,
+            max_backoff_secs: 30.0,
+            jitter: 0.5
+        }
+    }
+}
+
+impl DtlsReconnectPolicy {
+    // attempt starts from 1
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1)
+        .min(i32::MAX as u32) as i32;
+        let base = (self.initial_backoff_secs * 2f32.powi(exp))
+        .min(self.max_backoff_secs);
+        let jitter = self.jitter.clamp(0.0, 1.0) * rand::random::<f32>();
+        // infinite or too large backoff from config waits forever
+        Duration::try_from_secs_f32((base * (1.0 - jitter)).max(0.0))
+        .unwrap_or(Duration::MAX)
+    }
+}
+
+#[derive(Resource)]
+pub struct DtlsClientReconnector {
+    policy: DtlsReconnectPolicy,
+    attempt: u32,
+    timer: Option<Timer>,
+    gave_up: bool
+}
+
+impl DtlsClientReconnector {
+    #[inline]
+    pub fn new(policy: DtlsReconnectPolicy) -> Self {
+        Self{
+            policy,
+            attempt: 0,
+            timer: None,
+            gave_up: false
+        }
+    }
+
+    #[inline]
+    pub fn is_reconnecting(&self) -> bool {
+        self.attempt > 0
+    }
+
+    #[inline]
+    pub fn cancel(&mut self) {
+        self.attempt = 0;
+        self.timer = None;
+    }
+
+    fn schedule(&mut self) {
+        if self.attempt >= self.policy.max_attempts {
+            self.cancel();
+            self.gave_up = true;
+            return;
+        }
+
+        self.attempt += 1;
+        let backoff = self.policy.backoff(self.attempt);
+        debug!("reconnect attempt {} in {backoff:?}", self.attempt);
+        self.timer = Some(Timer::new(backoff, TimerMode::Once));
+    }
+}
+
+// only close without reason is unexpected,
+// reason is set by disconnect() or sent by server
+pub fn reconnect_watch_system(
+    mut reconnector: ResMut<DtlsClientReconnector>,
+    mut dtls_events: EventReader<DtlsClientEvent>
+) {
+    for e in dtls_events.read() {
+        match e {
+            DtlsClientEvent::ConnClosed { reason: None } 
+            if !reconnector.is_reconnecting() => {
+                reconnector.schedule();
+            }
+            DtlsClientEvent::ConnClosed { reason: Some(_) }
+            | DtlsClientEvent::Connected => {
+                reconnector.cancel();
+            }
+            DtlsClientEvent::ConnectFailed { .. } 
+            if reconnector.is_reconnecting() => {
+                reconnector.schedule();
+            }
+            _ => ()
+        }
+    }
+}
+
+pub fn reconnect_system(
+    time: Res<Time>,
+    mut reconnector: ResMut<DtlsClientReconnector>,
+    mut dtls_client: ResMut<DtlsClient>,
+    mut dtls_events: EventWriter<DtlsClientEvent>
+) {
+    if reconnector.gave_up {
+        reconnector.gave_up = false;
+        dtls_events.send(DtlsClientEvent::ReconnectGaveUp {
+            attempts: reconnector.policy.max_attempts
+        });
+        return;
+    }
+
+    if reconnector.is_reconnecting() && !dtls_client.can_reconnect() {
+        debug!("dtls client is disconnected explicitly, reconnect is canceled");
+        reconnector.cancel();
+        return;
+    }
+
+    let Some(ref mut timer) = reconnector.timer else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() {
+        return;
+    }
+
+    // started by someone else while waiting
+    if !dtls_client.is_closed() {
+        debug!("dtls client is not closed, reconnect is canceled");
+        reconnector.cancel();
+        return;
+    }
+
+    reconnector.timer = None;
+    let attempt = reconnector.attempt;
+    if let Err(e) = dtls_client.reconnect() {
+        debug!("reconnect is canceled: {e}");
+        reconnector.cancel();
+        return;
+    }
+
+    dtls_events.send(DtlsClientEvent::Reconnecting {
+        attempt
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_grows_until_max() {
+        let policy = DtlsReconnectPolicy{
+            jitter: 0.0,
+            ..default()
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_secs(1));
+        assert_eq!(policy.backoff(100), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn unbounded_backoff_does_not_panic() {
+        let policy = DtlsReconnectPolicy{
+            max_backoff_secs: f32::INFINITY,
+            jitter: 0.0,
+            ..default()
+        };
+        assert_eq!(policy.backoff(u32::MAX), Duration::MAX);
+
+        let policy = DtlsReconnectPolicy{
+            max_backoff_secs: f32::MAX,
+            jitter: 0.0,
+            ..default()
+        };
+        assert_eq!(policy.backoff(200), Duration::MAX);
+    }
+}
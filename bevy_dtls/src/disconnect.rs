@@ -0,0 +1,50 @@
+// application level reason sent to peer before close notify
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisconnectReason {
+    // disconnect is called without specific reason
+    Disconnected,
+    Kicked,
+    ServerShutdown,
+    VersionMismatch,
+    Banned,
+    // peer could not keep up with queue capacity
+    QueueFull,
+    // peer failed application level authentication
+    Unauthorized,
+    // pending conn is not approved in time
+    ApprovalTimeout,
+    // reserved for application, or unknown code from peer
+    Other(u8)
+}
+
+impl DisconnectReason {
+    #[inline]
+    pub fn to_code(self) -> u8 {
+        match self {
+            DisconnectReason::Disconnected => 0,
+            DisconnectReason::Kicked => 1,
+            DisconnectReason::ServerShutdown => 2,
+            DisconnectReason::VersionMismatch => 3,
+            DisconnectReason::Banned => 4,
+            DisconnectReason::QueueFull => 5,
+            DisconnectReason::Unauthorized => 6,
+            DisconnectReason::ApprovalTimeout => 7,
+            DisconnectReason::Other(c) => c
+        }
+    }
+
+    #[inline]
+    pub fn from_code(code: u8) -> Self {
+        match code {
+            0 => DisconnectReason::Disconnected,
+            1 => DisconnectReason::Kicked,
+            2 => DisconnectReason::ServerShutdown,
+            3 => DisconnectReason::VersionMismatch,
+            4 => DisconnectReason::Banned,
+            5 => DisconnectReason::QueueFull,
+            6 => DisconnectReason::Unauthorized,
+            7 => DisconnectReason::ApprovalTimeout,
+            c => DisconnectReason::Other(c)
+        }
+    }
+}
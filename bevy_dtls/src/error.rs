@@ -0,0 +1,189 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DtlsError {
+    // peer closed conn with close_notify or fatal alert,
+    // webrtc-dtls reports both as one error
+    #[error("closed by peer with close notify")]
+    CloseNotify,
+    // alert that did not close conn
+    #[error("alert: {0}")]
+    FatalAlert(String),
+    #[error("handshake failed: {0}")]
+    HandshakeFailed(String),
+    #[error("timeout")]
+    Timeout,
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("channel is closed")]
+    ChannelClosed,
+    #[error("conn is closed")]
+    ConnClosed,
+    #[error("conn {0} is not found")]
+    ConnNotFound(u64),
+    #[error("invalid state: {0}")]
+    InvalidState(&'static str),
+    #[error("certificate error: {0}")]
+    Certificate(String),
+    #[error("task error: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("transport error: {0}")]
+    Transport(String),
+    // reported by renet integration, kept as message 
+    // so that bevy_dtls does not depend on renet
+    #[error("renet error: {0}")]
+    Renet(String)
+}
+
+impl DtlsError {
+    // clean disconnection rather than error
+    #[inline]
+    pub fn is_disconnect(&self) -> bool {
+        matches!(self, DtlsError::CloseNotify | DtlsError::ConnClosed)
+    }
+
+    #[inline]
+    pub(crate) fn cert(err: anyhow::Error) -> Self {
+        DtlsError::Certificate(err.to_string())
+    }
+
+    fn from_dtls(err: &webrtc_dtls::Error) -> Self {
+        match err {
+            // returned by read after close_notify or fatal alert,
+            // conn is closed by peer in both cases
+            webrtc_dtls::Error::ErrAlertFatalOrClose => DtlsError::CloseNotify,
+            webrtc_dtls::Error::ErrConnClosed => DtlsError::ConnClosed,
+            webrtc_dtls::Error::ErrDeadlineExceeded => DtlsError::Timeout,
+            webrtc_dtls::Error::Other(msg) => match parse_alert(msg) {
+                Some(alert) => DtlsError::FatalAlert(alert.to_string()),
+                None => DtlsError::Transport(msg.clone())
+            },
+            e => DtlsError::Transport(e.to_string())
+        }
+    }
+}
+
+// warning alerts come only as message of Other, 
+// it is kept as message and never decides disconnection
+fn parse_alert(msg: &str) -> Option<&str> {
+    let start = msg.find("Alert ")? + "Alert ".len();
+    let alert = msg[start..].trim();
+    if alert.is_empty() {
+        return None;
+    }
+    Some(alert)
+}
+
+impl From<webrtc_dtls::Error> for DtlsError {
+    #[inline]
+    fn from(err: webrtc_dtls::Error) -> Self {
+        DtlsError::from_dtls(&err)
+    }
+}
+
+// webrtc-dtls errors on conn are wrapped into webrtc-util error
+impl From<webrtc_util::Error> for DtlsError {
+    fn from(err: webrtc_util::Error) -> Self {
+        if let Some(e) = err.downcast_ref::<webrtc_dtls::Error>() {
+            return DtlsError::from_dtls(e);
+        }
+
+        match err {
+            webrtc_util::Error::ErrTimeout => DtlsError::Timeout,
+            webrtc_util::Error::ErrClosedListener
+            | webrtc_util::Error::ErrUseClosedNetworkConn
+            | webrtc_util::Error::ErrAlreadyClosed => DtlsError::ConnClosed,
+            webrtc_util::Error::Io(e) => DtlsError::Io(e.0),
+            e => DtlsError::Transport(e.to_string())
+        }
+    }
+}
+
+impl<T> From<tokio::sync::mpsc::error::SendError<T>> for DtlsError {
+    #[inline]
+    fn from(_: tokio::sync::mpsc::error::SendError<T>) -> Self {
+        DtlsError::ChannelClosed
+    }
+}
+
+impl From<tokio::time::error::Elapsed> for DtlsError {
+    #[inline]
+    fn from(_: tokio::time::error::Elapsed) -> Self {
+        DtlsError::Timeout
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn close_or_fatal_alert_is_disconnect() {
+        let err = DtlsError::from(webrtc_dtls::Error::ErrAlertFatalOrClose);
+        assert!(matches!(err, DtlsError::CloseNotify));
+        assert!(err.is_disconnect());
+    }
+
+    #[test]
+    fn dtls_errors() {
+        assert!(matches!(
+            DtlsError::from(webrtc_dtls::Error::ErrConnClosed), 
+            DtlsError::ConnClosed
+        ));
+        assert!(matches!(
+            DtlsError::from(webrtc_dtls::Error::ErrDeadlineExceeded), 
+            DtlsError::Timeout
+        ));
+        assert!(matches!(
+            DtlsError::from(webrtc_dtls::Error::ErrContextUnsupported), 
+            DtlsError::Transport(_)
+        ));
+    }
+
+    #[test]
+    fn warning_alert_is_not_disconnect() {
+        let err = DtlsError::from(webrtc_dtls::Error::Other(
+            "Error of Alert Alert LevelWarning: NoRenegotiation".to_owned()
+        ));
+        match err {
+            DtlsError::FatalAlert(ref alert) => {
+                assert_eq!(alert, "Alert LevelWarning: NoRenegotiation");
+            }
+            ref e => panic!("unexpected {e:?}")
+        }
+        assert!(!err.is_disconnect());
+    }
+
+    #[test]
+    fn other_is_transport() {
+        let err = DtlsError::from(webrtc_dtls::Error::Other("no route".to_owned()));
+        assert!(matches!(err, DtlsError::Transport(ref m) if m == "no route"));
+    }
+
+    #[test]
+    fn util_errors() {
+        let err = DtlsError::from(webrtc_util::Error::from_std(
+            webrtc_dtls::Error::ErrAlertFatalOrClose
+        ));
+        assert!(matches!(err, DtlsError::CloseNotify));
+
+        assert!(matches!(
+            DtlsError::from(webrtc_util::Error::ErrTimeout), 
+            DtlsError::Timeout
+        ));
+        assert!(matches!(
+            DtlsError::from(webrtc_util::Error::ErrAlreadyClosed), 
+            DtlsError::ConnClosed
+        ));
+    }
+
+    #[test]
+    fn parse_alerts() {
+        assert_eq!(
+            parse_alert("Error of Alert Alert LevelFatal: HandshakeFailure"), 
+            Some("Alert LevelFatal: HandshakeFailure")
+        );
+        assert_eq!(parse_alert("Alert "), None);
+        assert_eq!(parse_alert("no route"), None);
+    }
+}
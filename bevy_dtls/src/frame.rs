@@ -0,0 +1,122 @@
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use crate::disconnect::DisconnectReason;
+
+// every datagram on dtls conn starts with one byte tag,
+// so that control messages never collide with application data
+const TAG_DATA: u8 = 0;
+const TAG_DISCONNECT: u8 = 1;
+const TAG_HEARTBEAT: u8 = 2;
+const TAG_PING: u8 = 3;
+const TAG_PONG: u8 = 4;
+
+pub(crate) enum Frame {
+    Data(Bytes),
+    Disconnect(DisconnectReason),
+    // keeps peer recv timeout from firing while idle
+    Heartbeat,
+    // answered by pong with same seq to measure rtt
+    Ping(u32),
+    Pong(u32)
+}
+
+#[inline]
+pub(crate) fn data(payload: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(payload.len() + 1);
+    buf.put_u8(TAG_DATA);
+    buf.put_slice(payload);
+    buf.freeze()
+}
+
+#[inline]
+pub(crate) fn disconnect(reason: DisconnectReason) -> Bytes {
+    Bytes::copy_from_slice(&[TAG_DISCONNECT, reason.to_code()])
+}
+
+#[inline]
+pub(crate) fn heartbeat() -> Bytes {
+    Bytes::from_static(&[TAG_HEARTBEAT])
+}
+
+#[inline]
+pub(crate) fn ping(seq: u32) -> Bytes {
+    seq_frame(TAG_PING, seq)
+}
+
+#[inline]
+pub(crate) fn pong(seq: u32) -> Bytes {
+    seq_frame(TAG_PONG, seq)
+}
+
+#[inline]
+fn seq_frame(tag: u8, seq: u32) -> Bytes {
+    let mut buf = BytesMut::with_capacity(5);
+    buf.put_u8(tag);
+    buf.put_u32(seq);
+    buf.freeze()
+}
+
+pub(crate) fn decode(datagram: Bytes) -> Option<Frame> {
+    match *datagram.first()? {
+        TAG_DATA => Some(Frame::Data(datagram.slice(1..))),
+        TAG_DISCONNECT => {
+            let code = *datagram.get(1)?;
+            Some(Frame::Disconnect(DisconnectReason::from_code(code)))
+        }
+        TAG_HEARTBEAT => Some(Frame::Heartbeat),
+        TAG_PING => Some(Frame::Ping(decode_seq(&datagram)?)),
+        TAG_PONG => Some(Frame::Pong(decode_seq(&datagram)?)),
+        _ => None
+    }
+}
+
+#[inline]
+fn decode_seq(datagram: &[u8]) -> Option<u32> {
+    let mut seq = datagram.get(1..5)?;
+    Some(seq.get_u32())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_round_trip() {
+        let payload = [TAG_DISCONNECT, 7, 8];
+        match decode(data(&payload)) {
+            Some(Frame::Data(p)) => assert_eq!(&p[..], &payload),
+            _ => panic!("not data")
+        }
+        assert!(matches!(decode(data(&[])), Some(Frame::Data(p)) if p.is_empty()));
+    }
+
+    #[test]
+    fn disconnect_round_trip() {
+        for reason in [
+            DisconnectReason::Disconnected,
+            DisconnectReason::Banned,
+            DisconnectReason::ApprovalTimeout,
+            DisconnectReason::Other(200)
+        ] {
+            assert!(matches!(
+                decode(disconnect(reason)), 
+                Some(Frame::Disconnect(r)) if r == reason
+            ));
+        }
+    }
+
+    #[test]
+    fn control_round_trip() {
+        assert!(matches!(decode(heartbeat()), Some(Frame::Heartbeat)));
+        assert!(matches!(decode(ping(u32::MAX)), Some(Frame::Ping(u32::MAX))));
+        assert!(matches!(decode(pong(42)), Some(Frame::Pong(42))));
+    }
+
+    #[test]
+    fn truncated_or_unknown() {
+        assert!(decode(Bytes::new()).is_none());
+        assert!(decode(Bytes::from_static(&[TAG_DISCONNECT])).is_none());
+        assert!(decode(Bytes::from_static(&[TAG_PING, 0, 0, 1])).is_none());
+        assert!(decode(Bytes::from_static(&[TAG_PONG])).is_none());
+        assert!(decode(Bytes::from_static(&[255, 0])).is_none());
+    }
+}
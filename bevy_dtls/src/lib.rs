@@ -1,15 +1,33 @@
 pub mod cert {
     pub mod loader;
+    pub mod psk;
+    pub mod fingerprint;
+    pub mod generator;
 }
 pub mod server {
     pub mod cert_option;
     pub mod dtls_server;
     pub mod plugin;
     pub mod event;
+    pub mod cert_watch;
+    pub mod peer_info;
+    pub mod limiter;
+    pub mod ip_filter;
+    pub(crate) mod conn_slab;
 }
 pub mod client {
     pub mod cert_option;
     pub mod dtls_client;
     pub mod plugin;
     pub mod event;
+    pub mod reconnect;
 }
+pub mod tuning;
+pub mod queue;
+pub mod stats;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod error;
+pub mod disconnect;
+pub(crate) mod frame;
+pub(crate) mod ping;
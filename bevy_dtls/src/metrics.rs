@@ -0,0 +1,249 @@
+use std::{
+    fmt::Write as _,
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{atomic::{AtomicBool, Ordering}, Arc, RwLock as StdRwLock},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant}
+};
+use bevy::prelude::*;
+use crate::{
+    server::dtls_server::DtlsServer,
+    stats::DtlsServerStats
+};
+
+// latest rendered text, shared with http thread
+#[derive(Resource, Clone, Default)]
+pub struct DtlsMetrics {
+    text: Arc<StdRwLock<String>>
+}
+
+impl DtlsMetrics {
+    // prometheus exposition format, for serving by your own server
+    #[inline]
+    pub fn text(&self) -> String {
+        self.text.read()
+        .unwrap()
+        .clone()
+    }
+}
+
+pub fn render(connected_clients: usize, stats: &DtlsServerStats) -> String {
+    let mut out = String::new();
+    let total = &stats.lifetime;
+
+    write_metric(&mut out, "connected_clients", "gauge",
+        "Connected clients.", connected_clients as u64);
+    write_metric(&mut out, "accepted_total", "counter",
+        "Accepted connections.", stats.accepted);
+    write_metric(&mut out, "rejected_max_clients_total", "counter",
+        "Connections rejected by max clients.", stats.rejected_max_clients);
+    write_metric(&mut out, "handshake_failures_total", "counter",
+        "Failed handshakes.", stats.handshake_failures);
+    write_metric(&mut out, "rejected_handshakes_full_total", "counter",
+        "Connections rejected by max concurrent handshakes.", stats.rejected_handshakes_full);
+    write_metric(&mut out, "rejected_handshake_rate_total", "counter",
+        "Connections rejected by handshake rate per ip.", stats.rejected_handshake_rate);
+    write_metric(&mut out, "rejected_conns_per_ip_total", "counter",
+        "Connections rejected by connections per ip.", stats.rejected_conns_per_ip);
+    write_metric(&mut out, "rejected_ip_filter_total", "counter",
+        "Connections rejected by ip filter or bans.", stats.rejected_ip_filter);
+    write_metric(&mut out, "rejected_draining_total", "counter",
+        "Connections rejected while draining.", stats.rejected_draining);
+    write_metric(&mut out, "received_bytes_total", "counter",
+        "Received bytes.", total.bytes_received);
+    write_metric(&mut out, "sent_bytes_total", "counter",
+        "Sent bytes.", total.bytes_sent);
+    write_metric(&mut out, "received_packets_total", "counter",
+        "Received datagrams.", total.packets_received);
+    write_metric(&mut out, "sent_packets_total", "counter",
+        "Sent datagrams.", total.packets_sent);
+    write_metric(&mut out, "send_timeouts_total", "counter",
+        "Send timeouts.", total.send_timeouts);
+    write_metric(&mut out, "recv_timeouts_total", "counter",
+        "Receive timeouts.", total.recv_timeouts);
+    write_metric(&mut out, "dropped_packets_total", "counter",
+        "Messages dropped by full queues.", total.dropped_packets);
+    out
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    // writing to string never fails
+    let _ = writeln!(out, "# HELP bevy_dtls_{name} {help}");
+    let _ = writeln!(out, "# TYPE bevy_dtls_{name} {kind}");
+    let _ = writeln!(out, "bevy_dtls_{name} {value}");
+}
+
+// how often non blocking accept checks shutdown flag
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+// whole time given to a client to send its request line,
+// so that slow client can not stall scrapes
+const REQUEST_TIMEOUT: Duration = Duration::from_millis(500);
+const MAX_REQUEST_LINE: usize = 1024;
+
+fn serve(listener: TcpListener, metrics: DtlsMetrics, shutdown: Arc<AtomicBool>) {
+    while !shutdown.load(Ordering::Acquire) {
+        let stream = match listener.accept() {
+            Ok((s, _)) => s,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(ACCEPT_POLL);
+                continue;
+            }
+            Err(e) => {
+                warn!("metrics endpoint failed to accept: {e}");
+                continue;
+            }
+        };
+
+        if let Err(e) = respond(stream, &metrics) {
+            debug!("metrics endpoint failed to respond: {e}");
+        }
+    }
+    debug!("metrics endpoint is stopped");
+}
+
+// any request is answered with metrics, only its request line is read
+fn read_request_line(stream: &mut TcpStream) -> std::io::Result<()> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut buf = [0; MAX_REQUEST_LINE];
+    let mut len = 0;
+    while len < buf.len() && !buf[..len].contains(&b'\n') {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(ErrorKind::TimedOut.into());
+        }
+
+        stream.set_read_timeout(Some(remaining))?;
+        match stream.read(&mut buf[len..])? {
+            0 => break,
+            n => len += n
+        }
+    }
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream, metrics: &DtlsMetrics)
+-> std::io::Result<()> {
+    // accepted socket may inherit non blocking mode of listener
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    read_request_line(&mut stream)?;
+
+    let body = metrics.text();
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/plain; version=0.0.4\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+#[derive(Resource)]
+struct DtlsMetricsTimer(Timer);
+
+// http thread serving DtlsMetrics, stopped on app exit or when dropped
+#[derive(Resource)]
+pub struct DtlsMetricsEndpoint {
+    shutdown: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl DtlsMetricsEndpoint {
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+        let Some(handle) = self.handle.take() else {
+            return;
+        };
+
+        if handle.join().is_err() {
+            error!("metrics endpoint thread panicked");
+        }
+    }
+}
+
+impl Drop for DtlsMetricsEndpoint {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn endpoint_exit_system(
+    mut exits: EventReader<AppExit>,
+    mut endpoint: ResMut<DtlsMetricsEndpoint>
+) {
+    if exits.read().next().is_some() {
+        endpoint.stop();
+    }
+}
+
+fn metrics_system(
+    time: Res<Time>,
+    mut timer: ResMut<DtlsMetricsTimer>,
+    metrics: Res<DtlsMetrics>,
+    dtls_server: Res<DtlsServer>
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let text = render(dtls_server.connected_clients(), &dtls_server.stats());
+    *metrics.text.write()
+    .unwrap() = text;
+}
+
+// renders DtlsServer stats periodically into DtlsMetrics resource,
+// and serves it over http if listen_addr is some
+pub struct DtlsMetricsPlugin {
+    pub listen_addr: Option<SocketAddr>,
+    pub refresh_secs: f32
+}
+
+impl Default for DtlsMetricsPlugin {
+    fn default() -> Self {
+        Self{
+            listen_addr: None,
+            refresh_secs: 5.0
+        }
+    }
+}
+
+impl Plugin for DtlsMetricsPlugin {
+    fn build(&self, app: &mut App) {
+        let metrics = DtlsMetrics::default();
+
+        if let Some(addr) = self.listen_addr {
+            let listener = match TcpListener::bind(addr)
+            .and_then(|l| l.set_nonblocking(true).map(|_| l)) {
+                Ok(l) => l,
+                Err(e) => panic!("failed to bind metrics endpoint {addr}: {e}")
+            };
+            let m = metrics.clone();
+            let shutdown = Arc::new(AtomicBool::new(false));
+            let s = Arc::clone(&shutdown);
+            let handle = thread::spawn(move || serve(listener, m, s));
+            info!("serving metrics on http://{addr}");
+
+            app.insert_resource(DtlsMetricsEndpoint{
+                shutdown,
+                handle: Some(handle)
+            })
+            .add_systems(Last,
+                endpoint_exit_system
+                .run_if(resource_exists::<DtlsMetricsEndpoint>)
+            );
+        }
+
+        app.insert_resource(metrics)
+        .insert_resource(DtlsMetricsTimer(Timer::from_seconds(
+            self.refresh_secs,
+            TimerMode::Repeating
+        )))
+        .add_systems(PostUpdate,
+            metrics_system
+            .run_if(resource_exists::<DtlsServer>)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant}
+};
+use tokio::time::{interval, Interval, MissedTickBehavior};
+
+// ping without pong for this long is counted as lost
+const LOSS_TIMEOUT: Duration = Duration::from_secs(3);
+// bounds memory if peer never answers
+const MAX_PENDING: usize = 64;
+
+#[derive(Default, Debug)]
+struct PingState {
+    next_seq: u32,
+    pending: VecDeque<(u32, Instant)>,
+    // smoothed as in RFC 6298
+    srtt: Option<Duration>,
+    rttvar: Duration,
+    answered: u64,
+    lost: u64
+}
+
+impl PingState {
+    fn expire(&mut self, now: Instant) {
+        while let Some(&(_, sent)) = self.pending.front() {
+            if now.duration_since(sent) < LOSS_TIMEOUT
+            && self.pending.len() <= MAX_PENDING {
+                break;
+            }
+
+            self.pending.pop_front();
+            self.lost += 1;
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct PingTracker {
+    state: Mutex<PingState>
+}
+
+impl PingTracker {
+    // returns seq of new ping
+    pub(crate) fn on_ping(&self) -> u32 {
+        let now = Instant::now();
+        let mut s = self.state.lock()
+        .unwrap();
+        s.expire(now);
+
+        let seq = s.next_seq;
+        s.next_seq = seq.wrapping_add(1);
+        s.pending.push_back((seq, now));
+        seq
+    }
+
+    pub(crate) fn on_pong(&self, seq: u32) {
+        let now = Instant::now();
+        let mut s = self.state.lock()
+        .unwrap();
+        s.expire(now);
+
+        // late or unknown pong
+        let Some(pos) = s.pending.iter()
+        .position(|&(q, _)| q == seq) else {
+            return;
+        };
+        let (_, sent) = s.pending.remove(pos)
+        .unwrap();
+        let sample = now.duration_since(sent);
+
+        match s.srtt {
+            None => {
+                s.srtt = Some(sample);
+                s.rttvar = sample / 2;
+            }
+            Some(srtt) => {
+                let diff = srtt.abs_diff(sample);
+                s.rttvar = (s.rttvar * 3 + diff) / 4;
+                s.srtt = Some((srtt * 7 + sample) / 8);
+            }
+        }
+        s.answered += 1;
+    }
+
+    // rtt, jitter, loss ratio
+    pub(crate) fn snapshot(&self) -> (Option<Duration>, Option<Duration>, Option<f32>) {
+        let s = self.state.lock()
+        .unwrap();
+        let total = s.answered + s.lost;
+        let loss = if total == 0 {
+            None
+        } else {
+            Some(s.lost as f32 / total as f32)
+        };
+
+        (s.srtt, s.srtt.map(|_| s.rttvar), loss)
+    }
+}
+
+#[inline]
+pub(crate) fn ping_interval(ping_secs: Option<u64>) -> Option<Interval> {
+    let mut i = interval(Duration::from_secs(ping_secs?.max(1)));
+    i.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    Some(i)
+}
+
+// never completes if ping is disabled, 
+// guard select branch with is_some so that else branch still works
+pub(crate) async fn tick(ping: &mut Option<Interval>) {
+    match ping {
+        Some(i) => {
+            i.tick().await;
+        }
+        None => std::future::pending().await
+    }
+}
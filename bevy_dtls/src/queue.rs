@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use async_channel::{Receiver, Sender, TrySendError};
+use crate::error::DtlsError;
+
+// what to do when a queue is full
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DtlsBackpressure {
+    DropNewest,
+    DropOldest,
+    // drops the message and disconnects peer with QueueFull reason
+    Disconnect
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DtlsQueueConfig {
+    // messages waiting for send loop, per conn
+    pub send_capacity: usize,
+    // messages waiting for recv(), per conn
+    pub recv_capacity: usize,
+    pub backpressure: DtlsBackpressure
+}
+
+impl Default for DtlsQueueConfig {
+    fn default() -> Self {
+        Self{
+            send_capacity: 1024,
+            recv_capacity: 4096,
+            backpressure: DtlsBackpressure::DropNewest
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct DropCounter {
+    dropped: AtomicU64,
+    overflowed: AtomicBool
+}
+
+impl DropCounter {
+    #[inline]
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    // true once after disconnect policy is triggered
+    #[inline]
+    pub(crate) fn take_overflowed(&self) -> bool {
+        self.overflowed.swap(false, Ordering::Relaxed)
+    }
+}
+
+#[inline]
+pub(crate) fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    async_channel::bounded(capacity.max(1))
+}
+
+// never waits, so that both ecs and loops can call this
+#[inline]
+pub(crate) fn push<T>(
+    tx: &Sender<T>, 
+    msg: T, 
+    backpressure: DtlsBackpressure,
+    counter: &DropCounter
+) -> Result<(), DtlsError> {
+    push_counted(tx, msg, backpressure, counter)
+    .map(|_| ())
+}
+
+// returns true if queue got one more message, 
+// false if msg is dropped or replaced the oldest one
+pub(crate) fn push_counted<T>(
+    tx: &Sender<T>, 
+    msg: T, 
+    backpressure: DtlsBackpressure,
+    counter: &DropCounter
+) -> Result<bool, DtlsError> {
+    let msg = match tx.try_send(msg) {
+        Ok(()) => return Ok(true),
+        Err(TrySendError::Full(m)) => m,
+        Err(TrySendError::Closed(_)) => return Err(DtlsError::ChannelClosed)
+    };
+
+    match backpressure {
+        DtlsBackpressure::DropNewest => {
+            counter.dropped.fetch_add(1, Ordering::Relaxed);
+            Ok(false)
+        }
+        DtlsBackpressure::DropOldest => {
+            match tx.force_send(msg) {
+                Ok(Some(_)) => {
+                    counter.dropped.fetch_add(1, Ordering::Relaxed);
+                    Ok(false)
+                }
+                // drained meanwhile
+                Ok(None) => Ok(true),
+                Err(_) => Err(DtlsError::ChannelClosed)
+            }
+        }
+        DtlsBackpressure::Disconnect => {
+            counter.dropped.fetch_add(1, Ordering::Relaxed);
+            counter.overflowed.store(true, Ordering::Relaxed);
+            Ok(false)
+        }
+    }
+}
@@ -1,15 +1,36 @@
-use rustls::RootCertStore;
+use std::{path::PathBuf, sync::Arc};
+use rustls::{pki_types::CertificateDer, RootCertStore};
 use webrtc_dtls::{
     config::{ClientAuthType, Config, ExtendedMasterSecretType}, 
     crypto::Certificate
 };
-use crate::cert::loader;
+use crate::cert::{
+    generator::{self, PemPair}, 
+    loader::{self, CertSource}, 
+    psk::{self, PskLookup}
+};
+
+// receives raw der chain of client, leaf first.
+// returning error rejects the handshake with it as reason
+pub type ClientCertVerifier = Arc<dyn Fn(&[Vec<u8>]) -> anyhow::Result<()> + Send + Sync>;
 
-#[derive(Clone, Copy)]
+// verify_peer_certificate of webrtc dtls config
+type DtlsCertVerifier = Arc<
+    dyn Fn(&[Vec<u8>], &[CertificateDer<'static>]) -> Result<(), webrtc_dtls::Error> 
+    + Send + Sync
+>;
+
+#[derive(Clone)]
 pub enum ServerCertOption {
     GenerateSelfSigned {
         subject_alt_name: &'static str
     },
+    // generates once and reuses cached files on subsequent starts
+    PersistSelfSigned {
+        subject_alt_name: String,
+        priv_key_path: PathBuf,
+        certificate_path: PathBuf
+    },
     Load {
         priv_key_path: &'static str,
         certificate_path: &'static str
@@ -17,11 +38,45 @@ pub enum ServerCertOption {
     LoadWithClientAuth {
         priv_key_path: &'static str,
         certificate_path: &'static str,
-        client_ca_path: &'static str
+        client_ca_path: &'static str,
+        client_verifier: Option<ClientCertVerifier>
+    },
+    LoadFrom {
+        priv_key: CertSource,
+        certificate: CertSource
+    },
+    LoadFromWithClientAuth {
+        priv_key: CertSource,
+        certificate: CertSource,
+        client_ca: CertSource,
+        client_verifier: Option<ClientCertVerifier>
+    },
+    Psk {
+        identity_hint: Option<Vec<u8>>,
+        psk_lookup: PskLookup
     }
 }
 
 impl ServerCertOption {
+    // pem of persisted self signed certificate, generated if not cached yet.
+    // pub_pem can be distributed to clients as root ca
+    pub fn self_signed_pem(&self) -> anyhow::Result<Option<PemPair>> {
+        let ServerCertOption::PersistSelfSigned { 
+            subject_alt_name, 
+            priv_key_path, 
+            certificate_path 
+        } = self else {
+            return Ok(None);
+        };
+
+        let pem_pair = generator::load_or_generate_self_signed(
+            vec![subject_alt_name.clone()], 
+            priv_key_path, 
+            certificate_path
+        )?;
+        Ok(Some(pem_pair))
+    }
+
     pub fn to_dtls_config(self) -> anyhow::Result<Config> {
         let config = match self {
             ServerCertOption::GenerateSelfSigned { 
@@ -37,6 +92,27 @@ impl ServerCertOption {
                     ..Default::default()
                 }
             }
+            ServerCertOption::PersistSelfSigned { 
+                subject_alt_name, 
+                priv_key_path, 
+                certificate_path 
+            } => {
+                let pem_pair = generator::load_or_generate_self_signed(
+                    vec![subject_alt_name], 
+                    priv_key_path, 
+                    certificate_path
+                )?;
+                let cert = loader::key_and_certificate_from(
+                    &CertSource::Pem(pem_pair.priv_pem.into_bytes()), 
+                    &CertSource::Pem(pem_pair.pub_pem.into_bytes())
+                )?;
+
+                Config{
+                    certificates: vec![cert],
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    ..Default::default()
+                }
+            }
             ServerCertOption::Load { priv_key_path, certificate_path } => {
                 let cert = loader::load_key_and_certificate(
                     priv_key_path.into(),
@@ -52,7 +128,8 @@ impl ServerCertOption {
             ServerCertOption::LoadWithClientAuth { 
                 priv_key_path, 
                 certificate_path,
-                client_ca_path 
+                client_ca_path,
+                client_verifier
             } => {
                 let cert = loader::load_key_and_certificate(
                     priv_key_path.into(),
@@ -70,6 +147,53 @@ impl ServerCertOption {
                     extended_master_secret: ExtendedMasterSecretType::Require,
                     client_auth: ClientAuthType::RequireAndVerifyClientCert,
                     client_cas: client_ca_store,
+                    verify_peer_certificate: client_verifier.map(to_dtls_verifier),
+                    ..Default::default()
+                }
+            }
+            ServerCertOption::LoadFrom { priv_key, certificate } => {
+                let cert = loader::key_and_certificate_from(
+                    &priv_key, 
+                    &certificate
+                )?;
+
+                Config{
+                    certificates: vec![cert],
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    ..Default::default()
+                }
+            }
+            ServerCertOption::LoadFromWithClientAuth { 
+                priv_key, 
+                certificate, 
+                client_ca,
+                client_verifier
+            } => {
+                let cert = loader::key_and_certificate_from(
+                    &priv_key, 
+                    &certificate
+                )?;
+
+                let mut client_ca_store = RootCertStore::empty();
+                for c in client_ca.to_certificate()? {
+                    client_ca_store.add(c)?;
+                }
+
+                Config{
+                    certificates: vec![cert],
+                    extended_master_secret: ExtendedMasterSecretType::Require,
+                    client_auth: ClientAuthType::RequireAndVerifyClientCert,
+                    client_cas: client_ca_store,
+                    verify_peer_certificate: client_verifier.map(to_dtls_verifier),
+                    ..Default::default()
+                }
+            }
+            ServerCertOption::Psk { identity_hint, psk_lookup } => {
+                Config{
+                    psk: Some(psk::to_dtls_psk_callback(psk_lookup)),
+                    psk_identity_hint: identity_hint,
+                    cipher_suites: psk::psk_cipher_suites(),
+                    extended_master_secret: ExtendedMasterSecretType::Require,
                     ..Default::default()
                 }
             }
@@ -78,3 +202,11 @@ impl ServerCertOption {
         Ok(config)
     }
 }
+
+#[inline]
+fn to_dtls_verifier(verifier: ClientCertVerifier) -> DtlsCertVerifier {
+    Arc::new(move |raw_certs: &[Vec<u8>], _: &[CertificateDer<'static>]| {
+        verifier(raw_certs)
+        .map_err(|e| webrtc_dtls::Error::Other(e.to_string()))
+    })
+}
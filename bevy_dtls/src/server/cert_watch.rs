@@ -0,0 +1,104 @@
+use std::{
+    path::{Path, PathBuf}, 
+    time::SystemTime
+};
+use bevy::prelude::*;
+use super::{
+    cert_option::ServerCertOption, 
+    dtls_server::DtlsServer, 
+    event::DtlsServerEvent
+};
+
+#[derive(Resource)]
+pub struct DtlsServerCertWatcher {
+    cert_option: ServerCertOption,
+    watch_paths: Vec<PathBuf>,
+    modified: Vec<Option<SystemTime>>,
+    timer: Timer
+}
+
+impl DtlsServerCertWatcher {
+    #[inline]
+    pub fn new(
+        cert_option: ServerCertOption, 
+        watch_paths: Vec<PathBuf>,
+        poll_interval_secs: f32
+    ) -> Self {
+        let modified = watch_paths.iter()
+        .map(modified_time)
+        .collect();
+
+        Self{
+            cert_option,
+            watch_paths,
+            modified,
+            timer: Timer::from_seconds(poll_interval_secs, TimerMode::Repeating)
+        }
+    }
+
+    fn is_modified(&mut self) -> bool {
+        let modified: Vec<Option<SystemTime>> = self.watch_paths.iter()
+        .map(modified_time)
+        .collect();
+
+        if modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        true
+    }
+}
+
+#[inline]
+fn modified_time(path: impl AsRef<Path>) -> Option<SystemTime> {
+    std::fs::metadata(path)
+    .and_then(|m| m.modified())
+    .ok()
+}
+
+pub fn cert_watch_system(
+    time: Res<Time>,
+    mut watcher: ResMut<DtlsServerCertWatcher>,
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    if !watcher.is_modified() {
+        return;
+    }
+
+    debug!("certificate files are modified, reloading");
+    match dtls_server.reload_cert(watcher.cert_option.clone()) {
+        Ok(()) => {
+            dtls_events.send(DtlsServerEvent::CertReloaded);
+        }
+        Err(e) => {
+            dtls_events.send(DtlsServerEvent::CertReloadFailed { 
+                err: e 
+            });
+        }
+    }
+}
+
+// polls modified time of watch paths, 
+// and reloads certificates of running server when it is changed
+pub struct DtlsServerCertWatchPlugin {
+    pub cert_option: ServerCertOption,
+    pub watch_paths: Vec<PathBuf>,
+    pub poll_interval_secs: f32
+}
+
+impl Plugin for DtlsServerCertWatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DtlsServerCertWatcher::new(
+            self.cert_option.clone(), 
+            self.watch_paths.clone(), 
+            self.poll_interval_secs
+        ))
+        .add_systems(PreUpdate, cert_watch_system);
+    }
+}
@@ -0,0 +1,177 @@
+use slab::Slab;
+use super::dtls_server::ConnIndex;
+
+// slots are reused, generation tells stale index apart
+pub(crate) struct ConnSlab<T> {
+    slab: Slab<T>,
+    // current generation of each slot, including vacant ones
+    generations: Vec<u32>
+}
+
+impl<T> Default for ConnSlab<T> {
+    fn default() -> Self {
+        Self{
+            slab: Slab::new(),
+            generations: vec![]
+        }
+    }
+}
+
+impl<T> ConnSlab<T> {
+    #[inline]
+    pub(crate) fn len(&self) -> usize {
+        self.slab.len()
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.slab.is_empty()
+    }
+
+    pub(crate) fn insert(&mut self, value: T) -> ConnIndex {
+        let slot = self.slab.vacant_key();
+        if slot == self.generations.len() {
+            // starts from 1, so that packed index is never 0
+            self.generations.push(1);
+        }
+
+        let generation = self.generations[slot];
+        self.slab.insert(value);
+        ConnIndex::new(slot as u32, generation)
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, idx: ConnIndex) -> Option<&T> {
+        if !self.is_current(idx) {
+            return None;
+        }
+        self.slab.get(idx.slot() as usize)
+    }
+
+    #[inline]
+    pub(crate) fn get_mut(&mut self, idx: ConnIndex) -> Option<&mut T> {
+        if !self.is_current(idx) {
+            return None;
+        }
+        self.slab.get_mut(idx.slot() as usize)
+    }
+
+    #[inline]
+    pub(crate) fn contains(&self, idx: ConnIndex) -> bool {
+        self.get(idx)
+        .is_some()
+    }
+
+    pub(crate) fn remove(&mut self, idx: ConnIndex) -> Option<T> {
+        if !self.is_current(idx) {
+            return None;
+        }
+
+        let slot = idx.slot() as usize;
+        let value = self.slab.try_remove(slot)?;
+        let generation = &mut self.generations[slot];
+        // wraps to 1, never 0
+        *generation = generation.checked_add(1)
+        .unwrap_or(1);
+        Some(value)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (ConnIndex, &T)> {
+        self.slab.iter()
+        .map(|(slot, v)| (ConnIndex::new(slot as u32, self.generations[slot]), v))
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (ConnIndex, &mut T)> {
+        let generations = &self.generations;
+        self.slab.iter_mut()
+        .map(|(slot, v)| (ConnIndex::new(slot as u32, generations[slot]), v))
+    }
+
+    #[inline]
+    pub(crate) fn indices(&self) -> Vec<ConnIndex> {
+        self.iter()
+        .map(|(idx, _)| idx)
+        .collect()
+    }
+
+    #[inline]
+    fn is_current(&self, idx: ConnIndex) -> bool {
+        self.generations.get(idx.slot() as usize)
+        .is_some_and(|&g| g == idx.generation())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_round_trip() {
+        let mut slab = ConnSlab::default();
+        let idx = slab.insert("a");
+        assert_ne!(idx.index(), 0);
+        assert_eq!(ConnIndex::from_index(idx.index()), idx);
+        assert_eq!(slab.get(ConnIndex::from_index(idx.index())), Some(&"a"));
+    }
+
+    #[test]
+    fn reused_slot_has_new_generation() {
+        let mut slab = ConnSlab::default();
+        let old = slab.insert("a");
+        assert_eq!(slab.remove(old), Some("a"));
+
+        let new = slab.insert("b");
+        assert_eq!(new.slot(), old.slot());
+        assert_ne!(new.generation(), old.generation());
+        assert_ne!(new.index(), old.index());
+    }
+
+    #[test]
+    fn stale_index_is_rejected() {
+        let mut slab = ConnSlab::default();
+        let old = slab.insert("a");
+        slab.remove(old);
+        let new = slab.insert("b");
+
+        assert!(!slab.contains(old));
+        assert_eq!(slab.get(old), None);
+        assert_eq!(slab.get_mut(old), None);
+        assert_eq!(slab.remove(old), None);
+        // stale remove does not touch current conn
+        assert_eq!(slab.get(new), Some(&"b"));
+        assert_eq!(slab.len(), 1);
+    }
+
+    #[test]
+    fn removed_twice() {
+        let mut slab = ConnSlab::default();
+        let idx = slab.insert("a");
+        assert_eq!(slab.remove(idx), Some("a"));
+        assert_eq!(slab.remove(idx), None);
+        assert!(slab.is_empty());
+    }
+
+    #[test]
+    fn iter_reports_current_indices() {
+        let mut slab = ConnSlab::default();
+        let a = slab.insert("a");
+        let b = slab.insert("b");
+        slab.remove(a);
+        let c = slab.insert("c");
+
+        let mut indices = slab.indices();
+        indices.sort_by_key(|idx| idx.slot());
+        assert_eq!(indices, vec![c, b]);
+
+        for (_, v) in slab.iter_mut() {
+            *v = "d";
+        }
+        assert_eq!(slab.get(c), Some(&"d"));
+    }
+
+    #[test]
+    fn unknown_slot() {
+        let slab = ConnSlab::<&str>::default();
+        assert!(!slab.contains(ConnIndex::new(3, 1)));
+    }
+}
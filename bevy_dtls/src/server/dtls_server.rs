@@ -1,10 +1,11 @@
 use std::{
-    collections::HashMap, 
-    net::IpAddr, 
-    sync::{Arc, RwLock as StdRwLock}, 
-    time::Duration
+    collections::VecDeque, 
+    future::Future, 
+    net::{IpAddr, SocketAddr}, 
+    pin::Pin, 
+    sync::{atomic::{AtomicBool, Ordering}, Arc}, 
+    time::{Duration, Instant, SystemTime}
 };
-use anyhow::{anyhow, bail};
 use bevy::{
     prelude::*, 
     tasks::futures_lite::future, 
@@ -12,27 +13,92 @@ use bevy::{
 use tokio::{
     runtime::{self, Runtime}, 
     select, 
-    sync::mpsc::{
-        error::TryRecvError, 
-        unbounded_channel as tokio_channel, 
-        UnboundedReceiver as TokioRx, 
-        UnboundedSender as TokioTx
+    sync::{
+        mpsc::{
+            error::TryRecvError, 
+            unbounded_channel as tokio_channel, 
+            UnboundedReceiver as TokioRx, 
+            UnboundedSender as TokioTx
+        },
+        watch,
+        OwnedSemaphorePermit,
+        Semaphore
     }, 
-    task::JoinHandle,
+    task::{JoinHandle, JoinSet},
     time::{timeout, sleep}
 };
-use webrtc_dtls::listener;
-use webrtc_util::conn::{Listener, Conn};
+use rustls::pki_types::CertificateDer;
+use webrtc_dtls::{
+    config::Config, 
+    conn::DTLSConn, 
+    content::ContentType
+};
+use webrtc_util::conn::{
+    conn_udp_listener::ListenConfig, 
+    Listener, 
+    Conn
+};
+use async_channel::{
+    Receiver as QueueRx, 
+    Sender as QueueTx, 
+    TryRecvError as QueueTryRecvError
+};
 use bytes::{Bytes, BytesMut};
-use super::cert_option::ServerCertOption;
+use crate::{
+    disconnect::DisconnectReason, 
+    error::DtlsError, 
+    frame::{self, Frame}, 
+    ping, 
+    queue::{self, DropCounter, DtlsQueueConfig}, 
+    stats::{DtlsConnStats, DtlsServerStats, ServerCounters, StatsCounter}, 
+    tuning::DtlsTuning
+};
+use super::{
+    cert_option::ServerCertOption, 
+    conn_slab::ConnSlab, 
+    ip_filter::{BanList, DtlsIpFilter}, 
+    limiter::{DtlsAcptLimits, HandshakeRate, IpConnSlot, IpConns}, 
+    peer_info::DtlsPeerInfo
+};
 
-#[derive(Clone, Copy, Debug)]
-pub struct ConnIndex(u64);
+// slot is reused after conn is closed, generation is bumped then.
+// packed index has generation in upper 32 bits and is never 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ConnIndex {
+    slot: u32,
+    generation: u32
+}
 
 impl ConnIndex {
+    #[inline]
+    pub(crate) fn new(slot: u32, generation: u32) -> Self {
+        Self{
+            slot,
+            generation
+        }
+    }
+
+    #[inline]
+    pub fn from_index(index: u64) -> Self {
+        Self{
+            slot: index as u32,
+            generation: (index >> 32) as u32
+        }
+    }
+
     #[inline]
     pub fn index(&self) -> u64 {
-        self.0
+        (self.generation as u64) << 32 | self.slot as u64
+    }
+
+    #[inline]
+    pub fn slot(&self) -> u32 {
+        self.slot
+    }
+
+    #[inline]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
 }
 
@@ -40,15 +106,30 @@ impl ConnIndex {
 pub struct DtlsServerConfig {
     pub listen_addr: IpAddr,
     pub listen_port: u16,
-    pub cert_option: ServerCertOption
+    pub cert_option: ServerCertOption,
+    pub tuning: DtlsTuning,
+    pub limits: DtlsAcptLimits,
+    pub ip_filter: DtlsIpFilter
 }
 
 impl DtlsServerConfig {
+    // handshake is done in acpt loop with current dtls config
+    // so that certificates can be swapped while listening
     async fn listen(self)
-    -> anyhow::Result<Arc<dyn Listener + Sync + Send>> {
-        let listener = listener::listen(
-            (self.listen_addr, self.listen_port), 
-            self.cert_option.to_dtls_config()?
+    -> Result<Arc<dyn Listener + Sync + Send>, DtlsError> {
+        let mut listen_config = ListenConfig{
+            accept_filter: Some(Box::new(
+                |packet: &[u8]| -> Pin<Box<dyn Future<Output = bool> + Send + 'static>> {
+                    let is_handshake = packet.first()
+                    .is_some_and(|t| *t == ContentType::Handshake as u8);
+                    Box::pin(async move { is_handshake })
+                }
+            )),
+            ..default()
+        };
+
+        let listener = listen_config.listen(
+            (self.listen_addr, self.listen_port)
         )
         .await?;
 
@@ -66,63 +147,223 @@ pub enum DtlsServerTimeout {
     Recv(ConnIndex)
 }
 
+#[derive(Debug)]
+pub enum DtlsRejectReason {
+    MaxClients,
+    ClientCert(String),
+    // max_handshakes of DtlsAcptLimits
+    TooManyHandshakes,
+    // handshakes_per_ip of DtlsAcptLimits
+    HandshakeRate,
+    // conns_per_ip of DtlsAcptLimits
+    ConnsPerIp,
+    // not allowed by DtlsIpFilter
+    Denied,
+    Banned,
+    // server is draining, see DtlsServer::drain
+    Draining
+}
+
+#[derive(Debug)]
+pub struct DtlsServerReject {
+    pub remote_addr: SocketAddr,
+    pub reason: DtlsRejectReason
+}
+
 struct DtlsServerClose;
 
+#[derive(Debug)]
+pub struct DtlsServerQueueFull {
+    pub conn_index: ConnIndex,
+    // total dropped messages of conn
+    pub dropped: u64,
+    // disconnect backpressure is triggered
+    pub disconnect: bool
+}
+
+#[derive(Debug)]
+pub enum DtlsServerDrain {
+    // conns left, reported when it changes
+    Remaining(usize),
+    // deadline passed, remaining conns have to be disconnected
+    DeadlinePassed(usize),
+    // no conns left
+    Drained
+}
+
+// set by drain(), cleared by start()
+struct DtlsServerDrainState {
+    deadline: Option<Instant>,
+    reported: Option<usize>,
+    deadline_passed: bool,
+    drained: bool
+}
+
 #[derive(Debug)]
 pub struct DtlsConnHealth {
     pub conn_index: ConnIndex,
-    pub sender: Option<anyhow::Result<()>>,
-    pub recver: Option<anyhow::Result<()>>,
-    pub closed: bool
+    pub sender: Option<Result<(), DtlsError>>,
+    pub recver: Option<Result<(), DtlsError>>,
+    pub closed: bool,
+    // set when closed, sent by peer or passed to disconnect
+    pub reason: Option<DisconnectReason>
 }
 
 #[derive(Debug)]
 pub struct DtlsServerHealth {
-    pub listener: Option<anyhow::Result<()>>,
+    pub listener: Option<Result<(), DtlsError>>,
     pub conns: Vec<DtlsConnHealth>
 }
 
+// handshaked conn waiting to be registered by acpt()
+struct DtlsServerAccepted {
+    conn: Arc<dyn Conn + Sync + Send>,
+    peer_info: DtlsPeerInfo
+}
+
 struct DtlsServerAcpter {
-    max_clients: usize,
     listener: Arc<dyn Listener + Sync + Send>,
-    conn_map: Arc<StdRwLock<HashMap<u64, DtlsConn>>>,
-    acpt_tx:  TokioTx<ConnIndex>,
+    // replaced by reload_cert
+    dtls_config: watch::Receiver<Config>,
+    limits: DtlsAcptLimits,
+    ip_filter: DtlsIpFilter,
+    handshake_rate: HandshakeRate,
+    // none is unlimited
+    handshake_permits: Option<Arc<Semaphore>>,
+    handshaker: DtlsServerHandshaker,
+    // aborted when acpt loop is closed by close_acpt_loop
+    handshakes: JoinSet<()>,
     close_rx: TokioRx<DtlsServerClose>
 }
 
 impl DtlsServerAcpter {
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         max_clients: usize,
         listener: Arc<dyn Listener + Sync + Send>,
-        conn_map: Arc<StdRwLock<HashMap<u64, DtlsConn>>>
-    ) -> (TokioRx<ConnIndex>, TokioTx<DtlsServerClose>, Self) {
-        let (acpt_tx, acpt_rx) = tokio_channel::<ConnIndex>();
+        dtls_config: watch::Receiver<Config>,
+        limits: DtlsAcptLimits,
+        ip_filter: DtlsIpFilter,
+        counters: Arc<ServerCounters>,
+        ip_conns: Arc<IpConns>,
+        bans: Arc<BanList>,
+        draining: Arc<AtomicBool>
+    ) -> (
+        TokioRx<DtlsServerAccepted>, 
+        TokioRx<DtlsServerReject>, 
+        TokioTx<DtlsServerClose>, 
+        Self
+    ) {
+        let (acpt_tx, acpt_rx) = tokio_channel::<DtlsServerAccepted>();
+        let (reject_tx, reject_rx) = tokio_channel::<DtlsServerReject>();
         let (close_tx, close_rx) = tokio_channel::<DtlsServerClose>();
 
-        (acpt_rx, close_tx, Self{
-            max_clients,
+        (acpt_rx, reject_rx, close_tx, Self{
             listener,
-            conn_map,
-            acpt_tx,
+            dtls_config,
+            handshake_rate: HandshakeRate::new(&limits),
+            handshake_permits: limits.max_handshakes
+            .map(|n| Arc::new(Semaphore::new(n))),
+            handshaker: DtlsServerHandshaker{
+                max_clients,
+                handshake_timeout: Duration::from_secs(limits.handshake_timeout_secs),
+                counters,
+                ip_conns,
+                bans,
+                draining,
+                acpt_tx,
+                reject_tx
+            },
+            handshakes: JoinSet::new(),
+            limits,
+            ip_filter,
             close_rx,
         })
     }
 
-    async fn acpt_loop(mut self) -> anyhow::Result<()> {
-        // start index from 1
-        // because server wants reserve 0
-        let mut index: u64 = 1;
+    // reports rejection by client certificate verifier 
+    // as this handshake's reject reason
+    fn wrap_client_verifier(&self, dtls_config: &mut Config, remote_addr: SocketAddr) {
+        let Some(verify) = dtls_config.verify_peer_certificate.take() else {
+            return;
+        };
+
+        let reject_tx = self.handshaker.reject_tx.clone();
+        dtls_config.verify_peer_certificate = Some(Arc::new(
+            move |raw_certs: &[Vec<u8>], chains: &[CertificateDer<'static>]| {
+                let result = verify(raw_certs, chains);
+                if let Err(ref e) = result {
+                    if let Err(e) = reject_tx.send(DtlsServerReject{
+                        remote_addr,
+                        reason: DtlsRejectReason::ClientCert(e.to_string())
+                    }) {
+                        debug!("reject rx looks already closed: {e}");
+                    }
+                }
+                result
+            }
+        ));
+    }
+
+    // checks limits before handshake,
+    // returns permit of handshake if none is exceeded
+    fn admit(&mut self, addr: SocketAddr) 
+    -> Result<Option<OwnedSemaphorePermit>, DtlsRejectReason> {
+        // draining is checked after handshake,
+        // peer only sees a timeout if it is rejected here
+        let ip = addr.ip();
+        if !self.ip_filter.is_allowed(ip) {
+            ServerCounters::incr(&self.handshaker.counters.rejected_ip_filter);
+            return Err(DtlsRejectReason::Denied);
+        }
+        if self.handshaker.bans.is_banned(ip) {
+            ServerCounters::incr(&self.handshaker.counters.rejected_ip_filter);
+            return Err(DtlsRejectReason::Banned);
+        }
 
+        if !self.handshake_rate.check(ip) {
+            ServerCounters::incr(&self.handshaker.counters.rejected_handshake_rate);
+            return Err(DtlsRejectReason::HandshakeRate);
+        }
+
+        let permit = match self.handshake_permits {
+            Some(ref s) => match Arc::clone(s).try_acquire_owned() {
+                Ok(p) => Some(p),
+                Err(_) => {
+                    ServerCounters::incr(&self.handshaker.counters.rejected_handshakes_full);
+                    return Err(DtlsRejectReason::TooManyHandshakes);
+                }
+            },
+            None => None
+        };
+
+        // released when conn is removed or handshake fails
+        if !self.handshaker.ip_conns.try_add(ip, self.limits.conns_per_ip) {
+            ServerCounters::incr(&self.handshaker.counters.rejected_conns_per_ip);
+            return Err(DtlsRejectReason::ConnsPerIp);
+        }
+
+        Ok(permit)
+    }
+
+    async fn acpt_loop(mut self) -> Result<(), DtlsError> {
         let result = loop {
             let (conn, addr) = select! {
                 biased;
 
                 Some(_) = self.close_rx.recv() => break Ok(()),
+                // reaps finished handshakes
+                Some(r) = self.handshakes.join_next() => {
+                    if let Err(e) = r {
+                        debug!("handshake task failed: {e}");
+                    }
+                    continue;
+                }
                 r = self.listener.accept() => {
                     match r {
                         Ok(ca) => ca,
-                        Err(e) => break Err(anyhow!(e)),
+                        Err(e) => break Err(DtlsError::from(e)),
                     }
                 }
                 else => {
@@ -134,67 +375,179 @@ impl DtlsServerAcpter {
                 }
             };
 
-            if self.conn_map.read()
-            .unwrap()
-            .len() >= self.max_clients {
-                warn!("{addr} is trying to connect, but exceeded max clients");
-                if let Err(e) = conn.close().await {
-                    error!("error on disconnect {addr}: {e}");
-                }
-                continue;
-            }
-
-            let idx = index;
-            index = match index.checked_add(1) {
-                Some(i) => i,
-                None => {
+            let permit = match self.admit(addr) {
+                Ok(p) => p,
+                Err(reason) => {
+                    debug!("{addr} is rejected before handshake: {reason:?}");
+                    self.handshaker.reject(addr, reason);
                     if let Err(e) = conn.close().await {
-                        error!("error on disconnect {addr}: {e}");
+                        debug!("error on closing {addr}: {e}");
                     }
-                    break Err(anyhow!("conn index overflow"));
+                    continue;
                 }
             };
-            
-            if let Err(e) = self.acpt_tx.send(ConnIndex(idx)) {
-                if let Err(e) = conn.close().await {
-                    error!("error on disconnect {addr}: {e}");
-                }
-                break Err(anyhow!(e));
-            }
 
-            let mut w = self.conn_map.write()
-            .unwrap();
-            debug_assert!(!w.contains_key(&idx));
+            // clone current config, reloaded certificates are used from next handshake
+            let mut dtls_config = self.dtls_config.borrow()
+            .clone();
+            self.wrap_client_verifier(&mut dtls_config, addr);
 
-            w.insert(idx, DtlsConn::new(conn));
-            debug!("conn from {addr} accepted");
+            // slow or silent peer does not block other handshakes
+            let slot = IpConnSlot::new(Arc::clone(&self.handshaker.ip_conns), addr.ip());
+            let handshaker = self.handshaker.clone();
+            self.handshakes.spawn(handshaker.handshake(conn, addr, dtls_config, permit, slot));
         };
 
+        // handshakes never push into acpt rx of closed server,
+        // their slots are released on drop
+        self.handshakes.shutdown().await;
         self.listener.close().await?;
         debug!("dtls server listener is closed");
         result
     }
 }
 
+#[derive(Clone)]
+struct DtlsServerHandshaker {
+    max_clients: usize,
+    handshake_timeout: Duration,
+    counters: Arc<ServerCounters>,
+    ip_conns: Arc<IpConns>,
+    bans: Arc<BanList>,
+    draining: Arc<AtomicBool>,
+    acpt_tx: TokioTx<DtlsServerAccepted>,
+    reject_tx: TokioTx<DtlsServerReject>
+}
+
+impl DtlsServerHandshaker {
+    #[inline]
+    fn reject(&self, remote_addr: SocketAddr, reason: DtlsRejectReason) {
+        if let Err(e) = self.reject_tx.send(DtlsServerReject{
+            remote_addr,
+            reason
+        }) {
+            debug!("reject rx looks already closed: {e}");
+        }
+    }
+
+    // permit is held until handshake is done,
+    // slot until conn is handed to server
+    async fn handshake(
+        self,
+        conn: Arc<dyn Conn + Sync + Send>,
+        addr: SocketAddr,
+        dtls_config: Config,
+        _permit: Option<OwnedSemaphorePermit>,
+        slot: IpConnSlot
+    ) {
+        let handshake = DTLSConn::new(conn, dtls_config, false, None);
+        let dtls_conn = match timeout(self.handshake_timeout, handshake).await {
+            Ok(Ok(c)) => c,
+            Ok(Err(e)) => {
+                warn!("handshake with {addr} failed: {e}");
+                ServerCounters::incr(&self.counters.handshake_failures);
+                return;
+            }
+            Err(_) => {
+                warn!("handshake with {addr} timed out");
+                ServerCounters::incr(&self.counters.handshake_failures);
+                return;
+            }
+        };
+        let peer_info = DtlsPeerInfo::from_conn(&dtls_conn, addr)
+        .await;
+        let conn: Arc<dyn Conn + Sync + Send> = Arc::new(dtls_conn);
+
+        // banned while handshaking
+        if self.bans.is_banned(addr.ip()) {
+            self.reject(addr, DtlsRejectReason::Banned);
+            ServerCounters::incr(&self.counters.rejected_ip_filter);
+            if let Err(e) = conn.close().await {
+                error!("error on disconnect {addr}: {e}");
+            }
+            return;
+        }
+
+        // peer is told why, so that it does not try to reconnect
+        if self.draining.load(Ordering::Acquire) {
+            self.reject(addr, DtlsRejectReason::Draining);
+            ServerCounters::incr(&self.counters.rejected_draining);
+            if let Err(e) = conn.send(&frame::disconnect(DisconnectReason::ServerShutdown))
+            .await {
+                debug!("could not send shutdown reason to {addr}: {e}");
+            }
+            if let Err(e) = conn.close().await {
+                error!("error on disconnect {addr}: {e}");
+            }
+            return;
+        }
+
+        // handshakes run concurrently, so reserve before checking
+        let conns = self.counters.conns.fetch_add(1, Ordering::AcqRel);
+        if conns >= self.max_clients {
+            self.counters.conns.fetch_sub(1, Ordering::AcqRel);
+            warn!("{addr} is trying to connect, but exceeded max clients");
+            self.reject(addr, DtlsRejectReason::MaxClients);
+            ServerCounters::incr(&self.counters.rejected_max_clients);
+            if let Err(e) = conn.close().await {
+                error!("error on disconnect {addr}: {e}");
+            }
+            return;
+        }
+
+        // counted until removed by health check
+        if let Err(e) = self.acpt_tx.send(DtlsServerAccepted{
+            conn: Arc::clone(&conn),
+            peer_info
+        }) {
+            debug!("acpt rx looks already closed: {e}");
+            self.counters.conns.fetch_sub(1, Ordering::AcqRel);
+            if let Err(e) = conn.close().await {
+                error!("error on disconnect {addr}: {e}");
+            }
+            return;
+        }
+
+        slot.keep();
+        ServerCounters::incr(&self.counters.accepted);
+        debug!("conn from {addr} accepted");
+    }
+}
+
 struct DtlsServerRecver {
     conn_idx: ConnIndex,
     conn: Arc<dyn Conn + Sync + Send>,
     buf_size: usize,
     timeout_secs: Option<u64>,
+    // pong is sent by recv loop, so it never waits longer than this
+    send_timeout_secs: u64,
 
-    recv_tx: TokioTx<(ConnIndex, Bytes)>,
+    queue_config: DtlsQueueConfig,
+    drop_counter: Arc<DropCounter>,
+    stats: Arc<StatsCounter>,
+
+    // own queue of this conn, so that backpressure never hits other conns
+    recv_tx: QueueTx<Bytes>,
+    // one index per queued message, tells recv() which queue to read
+    ready_tx: TokioTx<ConnIndex>,
     timeout_tx: TokioTx<DtlsServerTimeout>,
     close_rx: TokioRx<DtlsServerClose>
 }
 
 impl DtlsServerRecver {
     #[inline]
+    #[allow(clippy::too_many_arguments)]
     fn new(
         conn_idx: ConnIndex,
         conn: Arc<dyn Conn + Sync + Send>,
         buf_size: usize,
         timeout_secs: Option<u64>,
-        recv_tx: TokioTx<(ConnIndex, Bytes)>,
+        send_timeout_secs: u64,
+        queue_config: DtlsQueueConfig,
+        drop_counter: Arc<DropCounter>,
+        stats: Arc<StatsCounter>,
+        recv_tx: QueueTx<Bytes>,
+        ready_tx: TokioTx<ConnIndex>,
         timeout_tx: TokioTx<DtlsServerTimeout>
     ) -> (TokioTx<DtlsServerClose>, Self) {
         let (close_tx, close_rx) = tokio_channel::<DtlsServerClose>();
@@ -204,7 +557,12 @@ impl DtlsServerRecver {
             conn,
             buf_size,
             timeout_secs,
+            send_timeout_secs,
+            queue_config,
+            drop_counter,
+            stats,
             recv_tx,
+            ready_tx,
             timeout_tx,
             close_rx,
         })
@@ -218,7 +576,8 @@ impl DtlsServerRecver {
         }
     }
 
-    async fn recv_loop(mut self) -> anyhow::Result<()> {
+    async fn recv_loop(mut self) 
+    -> Result<Option<DisconnectReason>, DtlsError> {
         let mut buf = BytesMut::zeroed(self.buf_size);
         let timeout_dur = self.timeout_secs();
 
@@ -226,18 +585,19 @@ impl DtlsServerRecver {
             let (n, addr) = select! {
                 biased;
 
-                Some(_) = self.close_rx.recv() => break Ok(()),
+                Some(_) = self.close_rx.recv() => break Ok(None),
                 r = self.conn.recv_from(&mut buf) => {
                     match r {
                         Ok(na) => na,
-                        Err(e) => break Err(anyhow!("conn {:?}: {e}", self.conn_idx))
+                        Err(e) => break Err(DtlsError::from(e))
                     }
                 }
                 () = sleep(timeout_dur) => {
+                    self.stats.on_recv_timeout();
                     if let Err(e) = self.timeout_tx.send(
                         DtlsServerTimeout::Recv(self.conn_idx)
                     ) {
-                        break Err(anyhow!("conn {:?}: {e}", self.conn_idx));
+                        break Err(DtlsError::from(e));
                     }
                     continue;
                 }
@@ -247,32 +607,184 @@ impl DtlsServerRecver {
                         recver loop is closing anyway", 
                         self.conn_idx
                     );
-                    break Ok(());
+                    break Ok(None);
                 }
             };
 
             let recved = buf.split_to(n)
             .freeze();
-            if let Err(e) = self.recv_tx.send((self.conn_idx, recved)) {
-                break Err(anyhow!(e));
-            }
-
             buf.resize(self.buf_size, 0);
             trace!("received {n}bytes from {:?}:{addr}", self.conn_idx);
+
+            if let Some(r) = self.on_datagram(recved).await {
+                break r;
+            }
         };
 
         self.conn.close().await?;
         debug!("dtls server recv loop: {:?} is closed", self.conn_idx);
         result
     }
+
+    // continues after pending watcher has exited,
+    // datagrams buffered by it come first
+    async fn resume_loop(
+        mut self, 
+        watch_handle: JoinHandle<DtlsServerWatched>
+    ) -> Result<Option<DisconnectReason>, DtlsError> {
+        let watched = watch_handle.await?;
+        // conn is already closed by watcher
+        if let Some(r) = watched.closed {
+            return r;
+        }
+
+        for recved in watched.pending {
+            if let Some(r) = self.on_datagram(recved).await {
+                self.conn.close().await?;
+                debug!("dtls server recv loop: {:?} is closed", self.conn_idx);
+                return r;
+            }
+        }
+        self.recv_loop().await
+    }
+
+    // some if recv loop has to end
+    async fn on_datagram(&mut self, recved: Bytes) 
+    -> Option<Result<Option<DisconnectReason>, DtlsError>> {
+        self.stats.on_received(recved.len());
+
+        match frame::decode(recved) {
+            Some(Frame::Data(payload)) => {
+                match queue::push_counted(
+                    &self.recv_tx, 
+                    payload, 
+                    self.queue_config.backpressure, 
+                    &self.drop_counter
+                ) {
+                    Ok(true) => {
+                        if let Err(e) = self.ready_tx.send(self.conn_idx) {
+                            return Some(Err(DtlsError::from(e)));
+                        }
+                    }
+                    Ok(false) => (),
+                    Err(e) => return Some(Err(e))
+                }
+            }
+            Some(Frame::Disconnect(reason)) => {
+                debug!("{:?} is disconnecting: {reason:?}", self.conn_idx);
+                return Some(Ok(Some(reason)));
+            }
+            Some(Frame::Heartbeat) => trace!("heartbeat from {:?}", self.conn_idx),
+            Some(Frame::Ping(seq)) => {
+                match timeout(
+                    Duration::from_secs(self.send_timeout_secs), 
+                    self.conn.send(&frame::pong(seq))
+                )
+                .await {
+                    Ok(Ok(n)) => self.stats.on_sent(n),
+                    Ok(Err(e)) => return Some(Err(DtlsError::from(e))),
+                    Err(_) => debug!("sending pong to {:?} timed out", self.conn_idx)
+                }
+            }
+            Some(Frame::Pong(seq)) => self.stats.ping.on_pong(seq),
+            None => warn!("dropping unknown datagram from {:?}", self.conn_idx)
+        }
+        None
+    }
+}
+
+// result of pending watcher, handed to recv loop on approval
+struct DtlsServerWatched {
+    // raw datagrams received while pending
+    pending: VecDeque<Bytes>,
+    // some if peer closed conn before approval
+    closed: Option<Result<Option<DisconnectReason>, DtlsError>>
+}
+
+// reads pending conn to notice that peer has closed it,
+// and keeps datagrams until approval
+struct DtlsServerPendingWatcher {
+    conn_idx: ConnIndex,
+    conn: Arc<dyn Conn + Sync + Send>,
+    buf_size: usize,
+    capacity: usize,
+    close_rx: TokioRx<DtlsServerClose>
+}
+
+impl DtlsServerPendingWatcher {
+    #[inline]
+    fn new(
+        conn_idx: ConnIndex,
+        conn: Arc<dyn Conn + Sync + Send>,
+        buf_size: usize,
+        capacity: usize
+    ) -> (TokioTx<DtlsServerClose>, Self) {
+        let (close_tx, close_rx) = tokio_channel::<DtlsServerClose>();
+
+        (close_tx, Self{
+            conn_idx,
+            conn,
+            buf_size,
+            capacity,
+            close_rx
+        })
+    }
+
+    // stopped by start_conn, recv loop waits for it to exit
+    async fn watch_loop(mut self) -> DtlsServerWatched {
+        let mut buf = BytesMut::zeroed(self.buf_size);
+        let mut pending = VecDeque::new();
+
+        let result = loop {
+            let n = select! {
+                biased;
+
+                Some(_) = self.close_rx.recv() => {
+                    return DtlsServerWatched{
+                        pending,
+                        closed: None
+                    };
+                }
+                r = self.conn.recv(&mut buf) => {
+                    match r {
+                        Ok(n) => n,
+                        Err(e) => break Err(DtlsError::from(e))
+                    }
+                }
+            };
+
+            let recved = Bytes::copy_from_slice(&buf[..n]);
+            if let Some(Frame::Disconnect(reason)) = frame::decode(recved.clone()) {
+                debug!("pending {:?} is disconnecting: {reason:?}", self.conn_idx);
+                break Ok(Some(reason));
+            }
+
+            if pending.len() >= self.capacity {
+                debug!("dropping datagram from pending {:?}, buffer is full", self.conn_idx);
+                continue;
+            }
+            pending.push_back(recved);
+        };
+
+        if let Err(e) = self.conn.close().await {
+            debug!("error on closing pending {:?}: {e}", self.conn_idx);
+        }
+        debug!("dtls server pending watcher: {:?} is closed", self.conn_idx);
+        DtlsServerWatched{
+            pending,
+            closed: Some(result)
+        }
+    }
 }
 
 struct DtlsServerSender {
     conn_idx: ConnIndex,
     conn: Arc<dyn Conn + Sync + Send>,
     timeout_secs: u64,
+    ping_secs: Option<u64>,
+    stats: Arc<StatsCounter>,
 
-    send_rx: TokioRx<Bytes>,
+    send_rx: QueueRx<Bytes>,
     timeout_tx: TokioTx<DtlsServerTimeout>,
     close_rx: TokioRx<DtlsServerClose>
 }
@@ -283,15 +795,20 @@ impl DtlsServerSender {
         conn_idx: ConnIndex, 
         conn: Arc<dyn Conn + Sync + Send>,
         timeout_secs: u64,
+        ping_secs: Option<u64>,
+        send_capacity: usize,
+        stats: Arc<StatsCounter>,
         timeout_tx: TokioTx<DtlsServerTimeout>
-    ) -> (TokioTx<Bytes>, TokioTx<DtlsServerClose>, Self) {
-        let (send_tx, send_rx) = tokio_channel::<Bytes>();
+    ) -> (QueueTx<Bytes>, TokioTx<DtlsServerClose>, Self) {
+        let (send_tx, send_rx) = queue::channel::<Bytes>(send_capacity);
         let (close_tx, close_rx) = tokio_channel::<DtlsServerClose>();
  
         (send_tx, close_tx, Self{
             conn_idx,
             conn,
             timeout_secs,
+            ping_secs,
+            stats,
             send_rx,
             timeout_tx,
             close_rx
@@ -303,34 +820,55 @@ impl DtlsServerSender {
         Duration::from_secs(self.timeout_secs)
     }
 
-    async fn send_loop(mut self) -> anyhow::Result<()> {
+    async fn send_loop(mut self) -> Result<(), DtlsError> {
+        let mut ping_interval = ping::ping_interval(self.ping_secs);
+
         let result = loop {
             select! {
                 biased;
 
                 Some(_) = self.close_rx.recv() => break Ok(()),
-                Some(msg) = self.send_rx.recv() => {
+                Ok(msg) = self.send_rx.recv() => {
                     match timeout(
                         self.timeout_secs(),
-                        self.conn.send(&msg)
+                        self.conn.send(&frame::data(&msg))
                     )
                     .await {
                         Ok(r) => {
                             match r {
-                                Ok(n) => trace!("sent {n} bytes to {:?}", self.conn_idx),
-                                Err(e) => break Err(anyhow!("conn {:?}: {e}", self.conn_idx))
+                                Ok(n) => {
+                                    self.stats.on_sent(n);
+                                    trace!("sent {n} bytes to {:?}", self.conn_idx);
+                                }
+                                Err(e) => break Err(DtlsError::from(e))
                             }
                         }
                         Err(_) => {
+                            self.stats.on_send_timeout();
                             if let Err(e) = self.timeout_tx.send(DtlsServerTimeout::Send { 
                                 conn_index: self.conn_idx, 
                                 bytes: msg 
                             }) {
-                                break Err(anyhow!("conn {:?}: {e}", self.conn_idx));
+                                break Err(DtlsError::from(e));
                             }
                         }
                     }
                 }
+                () = ping::tick(&mut ping_interval), if ping_interval.is_some() => {
+                    let seq = self.stats.ping.on_ping();
+                    match timeout(
+                        self.timeout_secs(),
+                        self.conn.send(&frame::ping(seq))
+                    )
+                    .await {
+                        Ok(Ok(n)) => {
+                            self.stats.on_sent(n);
+                            trace!("sent ping {seq} to {:?}", self.conn_idx);
+                        }
+                        Ok(Err(e)) => break Err(DtlsError::from(e)),
+                        Err(_) => debug!("sending ping to {:?} timed out", self.conn_idx)
+                    }
+                }
                 else => {
                     warn!(
                         "is dtls conn {:?} closed before disconnection? \
@@ -350,27 +888,51 @@ impl DtlsServerSender {
 
 pub(super) struct DtlsConn {
     conn: Arc<dyn Conn + Sync + Send>,
+    peer_info: DtlsPeerInfo,
     is_running: bool,
+    acpted_at: Instant,
+    // runs while pending
+    watch_handle: Option<JoinHandle<DtlsServerWatched>>,
+    close_watch_tx: Option<TokioTx<DtlsServerClose>>,
+
+    disconnect_reason: Option<DisconnectReason>,
 
-    recv_handle: Option<JoinHandle<anyhow::Result<()>>>,
+    recv_handle: Option<JoinHandle<Result<Option<DisconnectReason>, DtlsError>>>,
     close_recv_tx: Option<TokioTx<DtlsServerClose>>,
+    recv_rx: Option<QueueRx<Bytes>>,
+
+    send_handle: Option<JoinHandle<Result<(), DtlsError>>>,
+    send_tx: Option<QueueTx<Bytes>>,
+    close_send_tx: Option<TokioTx<DtlsServerClose>>,
 
-    send_handle: Option<JoinHandle<anyhow::Result<()>>>,
-    send_tx: Option<TokioTx<Bytes>>,
-    close_send_tx: Option<TokioTx<DtlsServerClose>>
+    drop_counter: Arc<DropCounter>,
+    dropped_reported: u64,
+    stats: Arc<StatsCounter>
 }
 
 impl DtlsConn {
     #[inline]
-    pub(super) fn new(conn: Arc<dyn Conn + Sync + Send>) -> Self {
+    pub(super) fn new(
+        conn: Arc<dyn Conn + Sync + Send>, 
+        peer_info: DtlsPeerInfo
+    ) -> Self {
         Self{
             conn,
+            peer_info,
             is_running: false,
+            acpted_at: Instant::now(),
+            watch_handle: None,
+            close_watch_tx: None,
+            disconnect_reason: None,
             recv_handle: None,
             close_recv_tx: None,
+            recv_rx: None,
             send_handle: None,
             send_tx: None,
             close_send_tx: None,
+            drop_counter: default(),
+            dropped_reported: 0,
+            stats: default()
         }
     }
 }
@@ -381,18 +943,42 @@ pub struct DtlsServer {
     
     max_clients: usize,
     listener: Option<Arc<dyn Listener + Sync + Send>>,
-    acpt_handle: Option<JoinHandle<anyhow::Result<()>>>,
-    acpt_rx: Option<TokioRx<ConnIndex>>,
+    dtls_config: Option<watch::Sender<Config>>,
+    tuning: DtlsTuning,
+    limits: DtlsAcptLimits,
+    ip_filter: DtlsIpFilter,
+    acpt_handle: Option<JoinHandle<Result<(), DtlsError>>>,
+    acpt_rx: Option<TokioRx<DtlsServerAccepted>>,
+    reject_rx: Option<TokioRx<DtlsServerReject>>,
     close_acpt_tx: Option<TokioTx<DtlsServerClose>>,
     
-    conn_map: Arc<StdRwLock<HashMap<u64, DtlsConn>>>,
+    // owned by ecs, loops only hold clones of each conn
+    conns: ConnSlab<DtlsConn>,
+    counters: Arc<ServerCounters>,
+    ip_conns: Arc<IpConns>,
+    bans: Arc<BanList>,
+    // acpted conns stay pending until approve() if true
+    require_approval: bool,
+    // pending conns are rejected with ApprovalTimeout after this if some
+    pending_timeout_secs: Option<u64>,
+    // started by approve(), not reported by approve_check() yet
+    approved: VecDeque<ConnIndex>,
+    // shared with handshakes to disconnect new conns
+    draining: Arc<AtomicBool>,
+    drain: Option<DtlsServerDrainState>,
+    // sum of removed conns
+    closed_stats: DtlsConnStats,
 
     send_timeout_secs: u64,
+    ping_secs: Option<u64>,
 
     recv_buf_size: usize,
     recv_timeout_secs: Option<u64>,
-    recv_tx: Option<TokioTx<(ConnIndex, Bytes)>>,
-    recv_rx: Option<TokioRx<(ConnIndex, Bytes)>>,
+    // one index per message queued by each conn, so it is unbounded 
+    // but never longer than sum of recv_capacity of conns
+    ready_tx: Option<TokioTx<ConnIndex>>,
+    ready_rx: Option<TokioRx<ConnIndex>>,
+    queue_config: DtlsQueueConfig,
 
     timeout_tx: Option<TokioTx<DtlsServerTimeout>>,
     timeout_rx: Option<TokioRx<DtlsServerTimeout>>
@@ -404,8 +990,10 @@ impl DtlsServer {
         max_clients: usize,
         recv_buf_size: usize, 
         send_timeout_secs: u64,
-        recv_timeout_secs: Option<u64>
-    ) -> anyhow::Result<Self> {
+        recv_timeout_secs: Option<u64>,
+        queue_config: DtlsQueueConfig,
+        ping_secs: Option<u64>
+    ) -> Result<Self, DtlsError> {
         let rt = runtime::Builder::new_multi_thread()
         .enable_all()
         .build()?;
@@ -415,18 +1003,34 @@ impl DtlsServer {
 
             max_clients,
             listener: None, 
+            dtls_config: None,
+            tuning: default(),
+            limits: default(),
+            ip_filter: default(),
             acpt_handle: None,
             acpt_rx: None,
+            reject_rx: None,
             close_acpt_tx: None,
             
-            conn_map: default(),
+            conns: default(),
+            counters: default(),
+            ip_conns: default(),
+            bans: default(),
+            require_approval: false,
+            pending_timeout_secs: None,
+            approved: default(),
+            draining: default(),
+            drain: None,
+            closed_stats: default(),
 
             send_timeout_secs,
+            ping_secs,
 
             recv_timeout_secs,
             recv_buf_size,
-            recv_tx: None,
-            recv_rx: None,
+            ready_tx: None,
+            ready_rx: None,
+            queue_config,
 
             timeout_rx: None,
             timeout_tx: None
@@ -436,71 +1040,237 @@ impl DtlsServer {
     #[inline]
     pub fn is_closed(&self) -> bool {
         // set closed by conn healtch check
-        self.conn_map.read()
-        .unwrap()
-        .is_empty()        
+        self.conns
+        .is_empty()
         
         // set closed by call ing close
         &&self.listener.is_none()
         && self.acpt_handle.is_none()
         && self.acpt_rx.is_none()
+        && self.reject_rx.is_none()
         && self.close_acpt_tx.is_none()
-        && self.recv_tx.is_none()
-        && self.recv_rx.is_none()
+        && self.ready_tx.is_none()
+        && self.ready_rx.is_none()
         && self.timeout_rx.is_none()
         && self.timeout_tx.is_none()
     }
 
     #[inline]
     pub fn connected_clients(&self) -> usize {
-        let r = self.conn_map.read().unwrap();
-        r.len()
+        self.conns
+        .len()
     }
 
     #[inline]
     pub fn client_indices(&mut self) -> Vec<u64> {
-        let ks = {
-            self.conn_map.read()
-            .unwrap()
-            .keys()
-            .cloned()
-            .collect()
-        };
-        ks
+        self.conns
+        .iter()
+        .map(|(idx, _)| idx.index())
+        .collect()
     }
 
     #[inline]
     pub fn start(&mut self, config: DtlsServerConfig)
-    -> anyhow::Result<()> {
+    -> Result<(), DtlsError> {
         if !self.is_closed() {
-            bail!("dtls server is not closed");
+            return Err(DtlsError::InvalidState("dtls server is not closed"));
         }
 
+        self.drain = None;
+        self.draining.store(false, Ordering::Release);
         self.start_listen(config)?;
         self.start_acpt_loop()
     }
 
+    // new conns are handshaked and disconnected with ServerShutdown,
+    // current conns are let finish. conns left at deadline are reported by drain_check 
+    // to be disconnected with ServerShutdown, call close() after drained
+    pub fn drain(&mut self, deadline_secs: Option<u64>) {
+        if self.drain.is_some() {
+            return;
+        }
+
+        self.draining.store(true, Ordering::Release);
+        self.drain = Some(DtlsServerDrainState{
+            deadline: deadline_secs.map(|s| Instant::now() + Duration::from_secs(s)),
+            reported: None,
+            deadline_passed: false,
+            drained: false
+        });
+        debug!("dtls server is draining");
+    }
+
+    #[inline]
+    pub fn is_draining(&self) -> bool {
+        self.drain.is_some()
+    }
+
+    pub fn drain_check(&mut self) -> Option<DtlsServerDrain> {
+        let remaining = self.conns.len();
+        let drain = self.drain.as_mut()?;
+        if drain.drained {
+            return None;
+        }
+
+        if remaining == 0 {
+            drain.drained = true;
+            debug!("dtls server is drained");
+            return Some(DtlsServerDrain::Drained);
+        }
+
+        if !drain.deadline_passed
+        && drain.deadline.is_some_and(|d| Instant::now() >= d) {
+            drain.deadline_passed = true;
+            drain.reported = Some(remaining);
+            return Some(DtlsServerDrain::DeadlinePassed(remaining));
+        }
+
+        if drain.reported != Some(remaining) {
+            drain.reported = Some(remaining);
+            return Some(DtlsServerDrain::Remaining(remaining));
+        }
+        None
+    }
+
     #[inline]
     pub fn start_conn(&mut self, conn_index: ConnIndex)
-    -> anyhow::Result<()> {
+    -> Result<(), DtlsError> {
         self.start_recv_loop(conn_index)?;
         self.start_send_loop(conn_index)
     }
 
     #[inline]
     pub fn has_conn(&self, conn_idx: u64) -> bool {
-        self.conn_map.read()
-        .unwrap()
-        .contains_key(&conn_idx)
+        self.conns
+        .contains(ConnIndex::from_index(conn_idx))
     }
 
-    pub fn acpt(&mut self) -> Option<ConnIndex> {
-        let Some(ref mut acpt_rx) = self.acpt_rx else {
-            return None;
+    #[inline]
+    pub fn peer_info(&self, conn_idx: u64) -> Option<DtlsPeerInfo> {
+        self.conns
+        .get(ConnIndex::from_index(conn_idx))
+        .map(|c| c.peer_info.clone())
+    }
+
+    #[inline]
+    pub fn require_approval(&self) -> bool {
+        self.require_approval
+    }
+
+    #[inline]
+    pub fn set_require_approval(&mut self, require_approval: bool) {
+        self.require_approval = require_approval;
+    }
+
+    #[inline]
+    pub fn pending_timeout_secs(&self) -> Option<u64> {
+        self.pending_timeout_secs
+    }
+
+    #[inline]
+    pub fn set_pending_timeout_secs(&mut self, timeout_secs: Option<u64>) {
+        self.pending_timeout_secs = timeout_secs;
+    }
+
+    // acpted but neither approved nor rejected yet
+    #[inline]
+    pub fn is_pending(&self, conn_idx: u64) -> bool {
+        self.conns
+        .get(ConnIndex::from_index(conn_idx))
+        .is_some_and(|c| !c.is_running)
+    }
+
+    // starts pending conn, reported by approve_check
+    pub fn approve(&mut self, conn_index: u64) -> Result<(), DtlsError> {
+        let conn_idx = ConnIndex::from_index(conn_index);
+        match self.conns.get(conn_idx) {
+            Some(c) if c.is_running => {
+                return Err(DtlsError::InvalidState("conn is already started"));
+            }
+            Some(_) => (),
+            None => return Err(DtlsError::ConnNotFound(conn_index))
+        }
+
+        self.start_conn(conn_idx)?;
+        self.approved.push_back(conn_idx);
+        Ok(())
+    }
+
+    // closes pending conn without starting it,
+    // reason is sent to peer if some
+    pub fn reject(&mut self, conn_index: u64, reason: Option<DisconnectReason>) {
+        if !self.is_pending(conn_index) {
+            return;
+        }
+        let Some(dtls_conn) = self.remove_pending(ConnIndex::from_index(conn_index)) else {
+            return;
         };
 
+        let conn = dtls_conn.conn;
+        let timeout_dur = Duration::from_secs(self.send_timeout_secs);
+
+        self.runtime.spawn(async move {
+            if let Some(reason) = reason {
+                match timeout(timeout_dur, conn.send(&frame::disconnect(reason)))
+                .await {
+                    Ok(Ok(_)) => trace!("sent reject reason to {conn_index}"),
+                    Ok(Err(e)) => debug!("could not send reject reason to {conn_index}: {e}"),
+                    Err(_) => debug!("sending reject reason to {conn_index} timed out")
+                }
+            }
+
+            if let Err(e) = conn.close().await {
+                debug!("error on closing rejected conn {conn_index}: {e}");
+            }
+        });
+        debug!("pending conn {conn_index} is rejected");
+    }
+
+    fn remove_pending(&mut self, conn_idx: ConnIndex) -> Option<DtlsConn> {
+        let mut dtls_conn = self.conns.remove(conn_idx)?;
+        // conn is closed by caller, nothing is handed over
+        if let Some(handle) = dtls_conn.watch_handle.take() {
+            handle.abort();
+        }
+
+        self.counters.conns.fetch_sub(1, Ordering::AcqRel);
+        self.ip_conns.remove(dtls_conn.peer_info.remote_addr.ip());
+        Some(dtls_conn)
+    }
+
+    // conns started by approve(), skipping ones already removed
+    pub fn approve_check(&mut self) -> Option<ConnIndex> {
+        while let Some(idx) = self.approved.pop_front() {
+            if self.conns.contains(idx) {
+                return Some(idx);
+            }
+        }
+        None
+    }
+
+    pub fn acpt(&mut self) -> Option<ConnIndex> {
+        let acpt_rx = self.acpt_rx.as_mut()?;
+
         match acpt_rx.try_recv() {
-            Ok(a) => Some(a),
+            Ok(a) => {
+                let conn = Arc::clone(&a.conn);
+                let conn_idx = self.conns.insert(DtlsConn::new(a.conn, a.peer_info));
+                // without approval, conn is started right after acpt()
+                if self.require_approval {
+                    let (close_tx, watcher) = DtlsServerPendingWatcher::new(
+                        conn_idx,
+                        conn,
+                        self.recv_buf_size,
+                        self.queue_config.recv_capacity
+                    );
+                    let handle = self.runtime.spawn(watcher.watch_loop());
+                    if let Some(c) = self.conns.get_mut(conn_idx) {
+                        c.watch_handle = Some(handle);
+                        c.close_watch_tx = Some(close_tx);
+                    }
+                }
+                Some(conn_idx)
+            }
             Err(TryRecvError::Empty) => None,
             Err(e) => {
                 error!("acpt rx is closed before set to None: {e}");
@@ -509,42 +1279,52 @@ impl DtlsServer {
         }
     }
 
+    pub fn reject_check(&mut self) -> Option<DtlsServerReject> {
+        let reject_rx = self.reject_rx.as_mut()?;
+
+        match reject_rx.try_recv() {
+            Ok(r) => Some(r),
+            Err(TryRecvError::Empty) => None,
+            Err(e) => {
+                debug!("acpter loop looks closed before close: {e}");
+                None
+            }
+        }
+    }
+
     pub fn send(&self, conn_index: u64, message: Bytes) 
-    -> anyhow::Result<()> {
-        let r = self.conn_map.read()
-        .unwrap();
-        let Some(ref dtls_conn) = r.get(&conn_index) else {
-            bail!(
-                "conn {conn_index} is not started or is disconnected: \
-                dtls conn is None"
-            );
+    -> Result<(), DtlsError> {
+        let Some(dtls_conn) = self.conns.get(ConnIndex::from_index(conn_index)) else {
+            return Err(DtlsError::ConnNotFound(conn_index));
         };
+        // not started or is disconnected
         let Some(ref send_tx) = dtls_conn.send_tx else {
-            bail!(
-                "conn {conn_index} is not started or is disconnected: \
-                send tx is None"
-            );
+            return Err(DtlsError::ConnClosed);
         };
 
-        if let Err(e) = send_tx.send(message) {
-            bail!("conn {conn_index} is not started or is disconnected: {e}");
-        }
-        Ok(())
+        queue::push(
+            send_tx, 
+            message, 
+            self.queue_config.backpressure, 
+            &dtls_conn.drop_counter
+        )
     }
 
-    pub fn broadcast(&self, message: Bytes) -> anyhow::Result<()> {
-        let r = self.conn_map.read()
-        .unwrap();
-
-        for (idx, ref dtls_conn) in r.iter() {
+    pub fn broadcast(&self, message: Bytes) -> Result<(), DtlsError> {
+        for (idx, dtls_conn) in self.conns.iter() {
             let Some(ref send_tx) = dtls_conn.send_tx else {
-                warn!("skipping {idx} that is not started or already closed");
+                warn!("skipping {idx:?} that is not started or already closed");
                 continue;
             };
     
-            if let Err(e) = send_tx.send(message.clone()) {
+            if let Err(e) = queue::push(
+                send_tx, 
+                message.clone(), 
+                self.queue_config.backpressure, 
+                &dtls_conn.drop_counter
+            ) {
                 warn!(
-                    "skipping {idx} with error: {e} \
+                    "skipping {idx:?} with error: {e} \
                     that is not started or already closed"
                 );
                 continue;
@@ -555,17 +1335,32 @@ impl DtlsServer {
     }
 
     pub fn recv(&mut self) -> Option<(ConnIndex, Bytes)> {
-        let Some(ref mut recv_rx) = self.recv_rx else {
-            return None;
-        };
+        let ready_rx = self.ready_rx.as_mut()?;
+
+        loop {
+            let conn_idx = match ready_rx.try_recv() {
+                Ok(idx) => idx,
+                Err(TryRecvError::Empty) => return None,
+                Err(e) => {
+                    debug!("recver loops look closed before disconnection: {e}");
+                    return None;
+                }
+            };
 
-        match recv_rx.try_recv() {
-            Ok(ib) => Some(ib),
-            Err(e) => {
-                if matches!(e, TryRecvError::Disconnected) {
-                    debug!("recver loop looks closed before disconnection: {e}");
+            // conn is already removed
+            let Some(recv_rx) = self.conns.get(conn_idx)
+            .and_then(|c| c.recv_rx.as_ref()) else {
+                continue;
+            };
+
+            match recv_rx.try_recv() {
+                Ok(bytes) => return Some((conn_idx, bytes)),
+                Err(e) => {
+                    if matches!(e, QueueTryRecvError::Closed) {
+                        debug!("recver loop {conn_idx:?} looks closed: {e}");
+                    }
+                    continue;
                 }
-                None
             }
         }
     }
@@ -590,6 +1385,67 @@ impl DtlsServer {
         }
     }
 
+    #[inline]
+    pub fn dropped_packets(&self, conn_idx: u64) -> Option<u64> {
+        self.conns
+        .get(ConnIndex::from_index(conn_idx))
+        .map(|c| c.drop_counter.dropped())
+    }
+
+    pub fn conn_stats(&self, conn_idx: u64) -> Option<DtlsConnStats> {
+        self.conns
+        .get(ConnIndex::from_index(conn_idx))
+        .map(|c| c.stats.snapshot(c.drop_counter.dropped()))
+    }
+
+    pub fn stats(&self) -> DtlsServerStats {
+        let mut stats = DtlsServerStats{
+            lifetime: self.closed_stats,
+            accepted: self.counters.accepted
+            .load(Ordering::Relaxed),
+            rejected_max_clients: self.counters.rejected_max_clients
+            .load(Ordering::Relaxed),
+            handshake_failures: self.counters.handshake_failures
+            .load(Ordering::Relaxed),
+            rejected_handshakes_full: self.counters.rejected_handshakes_full
+            .load(Ordering::Relaxed),
+            rejected_handshake_rate: self.counters.rejected_handshake_rate
+            .load(Ordering::Relaxed),
+            rejected_conns_per_ip: self.counters.rejected_conns_per_ip
+            .load(Ordering::Relaxed),
+            rejected_ip_filter: self.counters.rejected_ip_filter
+            .load(Ordering::Relaxed),
+            rejected_draining: self.counters.rejected_draining
+            .load(Ordering::Relaxed),
+            ..default()
+        };
+
+        for (_, dtls_conn) in self.conns.iter() {
+            stats.add(&dtls_conn.stats.snapshot(dtls_conn.drop_counter.dropped()));
+        }
+        stats
+    }
+
+    // reports conns that dropped messages since last check
+    pub fn queue_check(&mut self) -> Vec<DtlsServerQueueFull> {
+        let mut fulls = vec![];
+        for (idx, dtls_conn) in self.conns.iter_mut() {
+            let dropped = dtls_conn.drop_counter.dropped();
+            let disconnect = dtls_conn.drop_counter.take_overflowed();
+            if dropped == dtls_conn.dropped_reported && !disconnect {
+                continue;
+            }
+
+            dtls_conn.dropped_reported = dropped;
+            fulls.push(DtlsServerQueueFull{
+                conn_index: idx,
+                dropped,
+                disconnect
+            });
+        }
+        fulls
+    }
+
     #[inline]
     pub fn health_check(&mut self) -> DtlsServerHealth {
         DtlsServerHealth{
@@ -598,90 +1454,212 @@ impl DtlsServer {
         }
     }
 
+    #[inline]
     pub fn disconnect(&mut self, conn_index: u64) {
-        let mut w = self.conn_map.write()
-        .unwrap();
-        if let Some(dtls_conn) = w.get_mut(&conn_index) {
-            if let Some(ref close_recv_tx) = dtls_conn.close_recv_tx {
-                if let Err(e) = close_recv_tx.send(DtlsServerClose) {
+        self.disconnect_with_reason(conn_index, DisconnectReason::Disconnected);
+    }
+
+    pub fn disconnect_with_reason(
+        &mut self, 
+        conn_index: u64, 
+        reason: DisconnectReason
+    ) {
+        if self.is_pending(conn_index) {
+            self.reject(conn_index, Some(reason));
+            return;
+        }
+        let Some(dtls_conn) = self.conns.get_mut(ConnIndex::from_index(conn_index)) else {
+            return;
+        };
+
+        let close_recv_tx = dtls_conn.close_recv_tx.take();
+        let close_send_tx = dtls_conn.close_send_tx.take();
+        dtls_conn.send_tx = None;
+        if close_recv_tx.is_none() && close_send_tx.is_none() {
+            return;
+        }
+
+        dtls_conn.disconnect_reason.get_or_insert(reason);
+        let conn = Arc::clone(&dtls_conn.conn);
+        let timeout_dur = Duration::from_secs(self.send_timeout_secs);
+
+        // reason has to reach peer before loops send close notify
+        self.runtime.spawn(async move {
+            match timeout(timeout_dur, conn.send(&frame::disconnect(reason)))
+            .await {
+                Ok(Ok(_)) => trace!("sent disconnect reason to {conn_index}"),
+                Ok(Err(e)) => debug!("could not send disconnect reason to {conn_index}: {e}"),
+                Err(_) => debug!("sending disconnect reason to {conn_index} timed out")
+            }
+
+            if let Some(tx) = close_recv_tx {
+                if let Err(e) = tx.send(DtlsServerClose) {
                     debug!("recver loop {conn_index} looks alredy closed: {e}");
                 }
-    
-                dtls_conn.close_recv_tx = None;    
-            };
-    
-            if let Some(ref close_send_tx) = dtls_conn.close_send_tx {
-                if let Err(e) = close_send_tx.send(DtlsServerClose) {
+            }
+            if let Some(tx) = close_send_tx {
+                if let Err(e) = tx.send(DtlsServerClose) {
                     debug!("sender loop {conn_index} looks already closed: {e}");
                 }
-    
-                dtls_conn.close_send_tx = None;
             }
-    
-            dtls_conn.send_tx = None;    
-        }
+        });
     }
 
+    #[inline]
     pub fn disconnect_all(&mut self) {
-        let ks: Vec<u64> = {
-            self.conn_map.read()
-            .unwrap()
-            .keys()
-            .cloned()
-            .collect()
-        };
+        self.disconnect_all_with_reason(DisconnectReason::Disconnected);
+    }
+
+    pub fn disconnect_all_with_reason(&mut self, reason: DisconnectReason) {
+        let ks = self.conns
+        .indices();
         
         for idx in ks {
-            self.disconnect(idx);
+            self.disconnect_with_reason(idx.index(), reason);
         }
     }
 
+    // disconnects conns from ip and rejects it until duration passes.
+    // none is permanent
+    // use RenetServerDtlsExt::ban_dtls with renet,
+    // so that renet clients are removed too
+    pub fn ban(&mut self, addr: IpAddr, duration: Option<Duration>)
+    -> Result<(), DtlsError> {
+        self.bans.ban(addr, duration);
+
+        for conn_index in self.conns_of_ip(addr) {
+            self.disconnect_with_reason(conn_index, DisconnectReason::Banned);
+        }
+
+        debug!("{addr} is banned");
+        self.save_bans()
+    }
+
+    // ipv4 mapped ipv6 addr is same as ipv4 one
+    pub fn conns_of_ip(&self, addr: IpAddr) -> Vec<u64> {
+        let addr = addr.to_canonical();
+        self.conns.iter()
+        .filter(|(_, c)| c.peer_info.remote_addr.ip().to_canonical() == addr)
+        .map(|(idx, _)| idx.index())
+        .collect()
+    }
+
+    pub fn unban(&mut self, addr: IpAddr) -> Result<(), DtlsError> {
+        if !self.bans.unban(addr) {
+            return Ok(());
+        }
+
+        debug!("{addr} is unbanned");
+        self.save_bans()
+    }
+
+    #[inline]
+    pub fn is_banned(&self, addr: IpAddr) -> bool {
+        self.bans.is_banned(addr)
+    }
+
+    // ip and expiry of bans, none is permanent
+    #[inline]
+    pub fn bans(&self) -> Vec<(IpAddr, Option<SystemTime>)> {
+        self.bans.bans()
+    }
+
+    // swaps certificates used for new handshakes,
+    // conns already accepted keep running
+    pub fn reload_cert(&mut self, cert_option: ServerCertOption)
+    -> Result<(), DtlsError> {
+        let Some(ref dtls_config) = self.dtls_config else {
+            return Err(DtlsError::InvalidState("dtls server is not started"));
+        };
+
+        let mut new_config = cert_option.to_dtls_config()
+        .map_err(DtlsError::cert)?;
+        self.tuning.apply(&mut new_config);
+        dtls_config.send_replace(new_config);
+
+        debug!("dtls server certificates are reloaded");
+        Ok(())
+    }
+
     pub fn close(&mut self) {
         self.close_acpt_loop();
 
-        self.recv_tx = None;
-        self.recv_rx = None;
+        self.ready_tx = None;
+        self.ready_rx = None;
         self.timeout_tx = None;
         self.timeout_rx = None;
     }
 
+    #[inline]
+    fn save_bans(&self) -> Result<(), DtlsError> {
+        match self.ip_filter.ban_file {
+            Some(ref path) => Ok(self.bans.save(path)?),
+            None => Ok(())
+        }
+    }
+
     fn start_listen(&mut self, config: DtlsServerConfig) 
-    -> anyhow::Result<()> {
+    -> Result<(), DtlsError> {
+        let mut dtls_config = config.cert_option
+        .clone()
+        .to_dtls_config()
+        .map_err(DtlsError::cert)?;
+        config.tuning.apply(&mut dtls_config);
+        self.tuning = config.tuning.clone();
+        self.limits = config.limits.clone();
+        self.ip_filter = config.ip_filter.clone();
+        if let Some(ref path) = self.ip_filter.ban_file {
+            self.bans.load(path)?;
+        }
         let listener = future::block_on(
             self.runtime.spawn(config.listen())
         )??;
         self.listener = Some(listener);
+        self.dtls_config = Some(watch::channel(dtls_config).0);
 
         Ok(())
     }
 
     fn start_acpt_loop(&mut self)
-    -> anyhow::Result<()> {
+    -> Result<(), DtlsError> {
         if self.acpt_handle.is_some() {
-            bail!("join handle exists, or health_check is not called");
+            return Err(DtlsError::InvalidState(
+                "join handle exists, or health_check is not called"
+            ));
         }
 
-        let (recv_tx, recv_rx) = tokio_channel::<(ConnIndex, Bytes)>();
-        self.recv_tx = Some(recv_tx);
-        self.recv_rx = Some(recv_rx);
+        let (ready_tx, ready_rx) = tokio_channel::<ConnIndex>();
+        self.ready_tx = Some(ready_tx);
+        self.ready_rx = Some(ready_rx);
         let (timeout_tx, timeout_rx) = tokio_channel::<DtlsServerTimeout>();
         self.timeout_tx = Some(timeout_tx);
         self.timeout_rx = Some(timeout_rx);
 
         let (
             acpt_rx,
+            reject_rx,
             close_tx,
             acpter
         ) = DtlsServerAcpter::new(
             self.max_clients,
             match self.listener {
                 Some(ref l) => Arc::clone(l),
-                None => bail!("listener is None")
+                None => return Err(DtlsError::InvalidState("listener is None"))
             }, 
-            Arc::clone(&self.conn_map)
+            match self.dtls_config {
+                Some(ref c) => c.subscribe(),
+                None => return Err(DtlsError::InvalidState("dtls config is None"))
+            },
+            self.limits.clone(),
+            self.ip_filter.clone(),
+            Arc::clone(&self.counters),
+            Arc::clone(&self.ip_conns),
+            Arc::clone(&self.bans),
+            Arc::clone(&self.draining)
         );
         
         self.acpt_rx = Some(acpt_rx);
+        self.reject_rx = Some(reject_rx);
         self.close_acpt_tx = Some(close_tx);
         
         let handle = self.runtime.spawn(acpter.acpt_loop());
@@ -692,7 +1670,7 @@ impl DtlsServer {
     }
 
     fn health_check_acpt(&mut self) 
-    -> Option<anyhow::Result<()>> {
+    -> Option<Result<(), DtlsError>> {
         let handle_ref = self.acpt_handle.as_ref()?;
 
         if !handle_ref.is_finished() {
@@ -703,51 +1681,83 @@ impl DtlsServer {
         self.listener = None;
         match future::block_on(handle) {
             Ok(r) => Some(r),
-            Err(e) => Some(Err(anyhow!(e)))
+            Err(e) => Some(Err(DtlsError::from(e)))
         }
     }
 
     fn close_acpt_loop(&mut self) {
+        // acpt loop aborts running handshakes on exit
         if let Some(ref close_acpt_tx) = self.close_acpt_tx {
             if let Err(e) = close_acpt_tx.send(DtlsServerClose) {
                 debug!("acpter loop looks already closed: {e}");
             }
         }
 
+        // handshaked but not registered by acpt() yet
+        if let Some(mut acpt_rx) = self.acpt_rx.take() {
+            while let Ok(a) = acpt_rx.try_recv() {
+                self.counters.conns.fetch_sub(1, Ordering::AcqRel);
+                self.ip_conns.remove(a.peer_info.remote_addr.ip());
+                self.runtime.spawn(async move {
+                    if let Err(e) = a.conn.close().await {
+                        debug!("error on closing pending conn: {e}");
+                    }
+                });
+            }
+        }
+
         self.close_acpt_tx = None;
-        self.acpt_rx = None;
+        self.reject_rx = None;
+        self.dtls_config = None;
     }
 
-    fn start_recv_loop(&self, conn_idx: ConnIndex) 
-    -> anyhow::Result<()> {
-        let mut w = self.conn_map.write()
-        .unwrap();
-        let Some(dtls_conn) = w.get_mut(&conn_idx.0) else {
-            bail!("dtls conn {conn_idx:?} is None");
+    fn start_recv_loop(&mut self, conn_idx: ConnIndex) 
+    -> Result<(), DtlsError> {
+        let Some(dtls_conn) = self.conns.get_mut(conn_idx) else {
+            return Err(DtlsError::ConnNotFound(conn_idx.index()));
         };
 
         if dtls_conn.recv_handle.is_some() {
-            bail!("join handle already exists, or health_check is not called");
+            return Err(DtlsError::InvalidState(
+                "join handle already exists, or health_check is not called"
+            ));
         }
 
+        let (recv_tx, recv_rx) = queue::channel::<Bytes>(self.queue_config.recv_capacity);
         let (close_tx, recver) = DtlsServerRecver::new(
             conn_idx, 
             Arc::clone(&dtls_conn.conn), 
             self.recv_buf_size, 
             self.recv_timeout_secs, 
-            match self.recv_tx {
+            self.send_timeout_secs,
+            self.queue_config,
+            Arc::clone(&dtls_conn.drop_counter),
+            Arc::clone(&dtls_conn.stats),
+            recv_tx,
+            match self.ready_tx {
                 Some(ref tx) => tx.clone(),
-                None => bail!("recv tx is still None")
+                None => return Err(DtlsError::InvalidState("ready tx is still None"))
             },
             match self.timeout_tx {
                 Some(ref tx) => tx.clone(),
-                None => bail!("timeout tx is still None")
+                None => return Err(DtlsError::InvalidState("timeout tx is still None"))
             }
         );
 
         dtls_conn.close_recv_tx = Some(close_tx);
+        dtls_conn.recv_rx = Some(recv_rx);
 
-        let handle = self.runtime.spawn(recver.recv_loop());
+        let handle = match dtls_conn.watch_handle.take() {
+            Some(watch_handle) => {
+                if let Some(tx) = dtls_conn.close_watch_tx.take() {
+                    if let Err(e) = tx.send(DtlsServerClose) {
+                        debug!("pending watcher {conn_idx:?} looks already closed: {e}");
+                    }
+                }
+                self.runtime.spawn(recver.resume_loop(watch_handle))
+            }
+            None => self.runtime.spawn(recver.recv_loop())
+        };
         dtls_conn.recv_handle = Some(handle);
         dtls_conn.is_running = true;
         
@@ -756,24 +1766,27 @@ impl DtlsServer {
     }
 
     fn start_send_loop(&mut self, conn_idx: ConnIndex) 
-    -> anyhow::Result<()> {
-        let mut w = self.conn_map.write()
-        .unwrap();
-        let Some(dtls_conn) = w.get_mut(&conn_idx.0) else {
-            bail!("dtls conn: {conn_idx:?} is None");
+    -> Result<(), DtlsError> {
+        let Some(dtls_conn) = self.conns.get_mut(conn_idx) else {
+            return Err(DtlsError::ConnNotFound(conn_idx.index()));
         };
 
         if dtls_conn.send_handle.is_some() {
-            bail!("join handle already exists, or health_check is not called");
+            return Err(DtlsError::InvalidState(
+                "join handle already exists, or health_check is not called"
+            ));
         }
 
         let (send_tx, close_tx, sender) = DtlsServerSender::new(
             conn_idx, 
             Arc::clone(&dtls_conn.conn), 
             self.send_timeout_secs,
+            self.ping_secs,
+            self.queue_config.send_capacity,
+            Arc::clone(&dtls_conn.stats),
             match self.timeout_tx {
                 Some(ref tx) => tx.clone(),
-                None => bail!("timeout tx is still None")
+                None => return Err(DtlsError::InvalidState("timeout tx is still None"))
             }
         );
 
@@ -790,13 +1803,11 @@ impl DtlsServer {
 
     fn health_check_conn_loop(&mut self)
     -> Vec<DtlsConnHealth> {
-        let mut conns_health = vec![];
+        let mut conns_health = self.health_check_pending();
             
         let conn_statuses = {
             let mut s = vec![];
-            let r = self.conn_map.read()
-            .unwrap();
-            for (idx, dtls_conn) in r.iter() {
+            for (idx, dtls_conn) in self.conns.iter() {
                 let sender_finished = if let Some(ref handle_ref) = dtls_conn.send_handle {
                     handle_ref.is_finished()
                 } else {
@@ -810,17 +1821,16 @@ impl DtlsServer {
                 }; 
 
                 if sender_finished || recver_finished {
-                    s.push((*idx, sender_finished, recver_finished));
+                    s.push((idx, sender_finished, recver_finished));
                 }
             }
             s
         };
 
-        let mut w = self.conn_map.write()
-        .unwrap();
         for (idx, sender_finished, recver_finished) in conn_statuses {
-            let dtls_conn = w.get_mut(&idx)
-            .unwrap();
+            let Some(dtls_conn) = self.conns.get_mut(idx) else {
+                continue;
+            };
 
             let sender_health = if sender_finished {
                 let handle = dtls_conn.send_handle
@@ -828,7 +1838,7 @@ impl DtlsServer {
                 .unwrap();
                 let r = match future::block_on(handle) {
                     Ok(r) => r,
-                    Err(e) => Err(anyhow!(e))
+                    Err(e) => Err(DtlsError::from(e))
                 };
                 Some(r)
             } else {
@@ -840,8 +1850,20 @@ impl DtlsServer {
                 .take()
                 .unwrap();
                 let r = match future::block_on(handle) {
-                    Ok(r) => r,
-                    Err(e) => Err(anyhow!(e))
+                    Ok(Ok(Some(reason))) => {
+                        // peer is leaving, sender loop is no longer needed
+                        dtls_conn.disconnect_reason.get_or_insert(reason);
+                        if let Some(tx) = dtls_conn.close_send_tx.take() {
+                            if let Err(e) = tx.send(DtlsServerClose) {
+                                debug!("sender loop {idx:?} looks already closed: {e}");
+                            }
+                        }
+                        dtls_conn.close_recv_tx = None;
+                        dtls_conn.send_tx = None;
+                        Ok(())
+                    }
+                    Ok(r) => r.map(|_| ()),
+                    Err(e) => Err(DtlsError::from(e))
                 };
                 Some(r)
             } else {
@@ -852,15 +1874,88 @@ impl DtlsServer {
             && dtls_conn.send_handle.is_none()
             && dtls_conn.recv_handle.is_none();
         
-            if closed {
-                w.remove(&idx);
-            }
+            let reason = if closed {
+                self.conns.remove(idx)
+                .and_then(|c| {
+                    self.counters.conns.fetch_sub(1, Ordering::AcqRel);
+                    self.ip_conns.remove(c.peer_info.remote_addr.ip());
+                    let stats = c.stats.snapshot(c.drop_counter.dropped());
+                    self.closed_stats.accumulate(&stats);
+                    c.disconnect_reason
+                })
+            } else {
+                None
+            };
 
             conns_health.push(DtlsConnHealth{
-                conn_index: ConnIndex(idx),
+                conn_index: idx,
                 sender: sender_health,
                 recver: recver_health,
-                closed
+                closed,
+                reason
+            });
+        }
+        conns_health
+    }
+
+    // removes pending conns closed by peer or not approved in time
+    fn health_check_pending(&mut self) -> Vec<DtlsConnHealth> {
+        let pending_timeout = self.pending_timeout_secs
+        .map(Duration::from_secs);
+        let (closed, expired) = {
+            let mut closed = vec![];
+            let mut expired = vec![];
+            for (idx, dtls_conn) in self.conns.iter() {
+                if dtls_conn.is_running {
+                    continue;
+                }
+
+                if dtls_conn.watch_handle
+                .as_ref()
+                .is_some_and(|h| h.is_finished()) {
+                    closed.push(idx);
+                } else if pending_timeout.is_some_and(|t| dtls_conn.acpted_at.elapsed() >= t) {
+                    expired.push(idx);
+                }
+            }
+            (closed, expired)
+        };
+
+        let mut conns_health = vec![];
+        for idx in closed {
+            // taken before removal, which aborts it
+            let Some(handle) = self.conns.get_mut(idx)
+            .and_then(|c| c.watch_handle.take()) else {
+                continue;
+            };
+            self.remove_pending(idx);
+
+            let (recver, reason) = match future::block_on(handle) {
+                Ok(DtlsServerWatched{closed: Some(Ok(reason)), ..}) => (Ok(()), reason),
+                Ok(DtlsServerWatched{closed: Some(Err(e)), ..}) => (Err(e), None),
+                Ok(DtlsServerWatched{closed: None, ..}) => (Ok(()), None),
+                Err(e) => (Err(DtlsError::from(e)), None)
+            };
+            debug!("pending conn {idx:?} is closed before approval");
+            conns_health.push(DtlsConnHealth{
+                conn_index: idx,
+                sender: None,
+                recver: Some(recver),
+                closed: true,
+                reason
+            });
+        }
+
+        for idx in expired {
+            let reason = DisconnectReason::ApprovalTimeout;
+            self.reject(idx.index(), Some(reason));
+            debug!("pending conn {idx:?} is not approved in time");
+            conns_health.push(DtlsConnHealth{
+                conn_index: idx,
+                sender: None,
+                recver: None,
+                closed: true,
+                reason: Some(reason)
             });
         }
         conns_health
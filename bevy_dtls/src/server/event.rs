@@ -1,10 +1,26 @@
-use anyhow::anyhow;
 use bevy::prelude::*;
 use bytes::Bytes;
-use super::dtls_server::{DtlsServer, DtlsServerTimeout};
+use crate::{disconnect::DisconnectReason, error::DtlsError};
+use std::net::SocketAddr;
+use super::{
+    dtls_server::{DtlsRejectReason, DtlsServer, DtlsServerDrain, DtlsServerTimeout}, 
+    peer_info::DtlsPeerInfo
+};
 
 #[derive(Event, Debug)]
 pub enum DtlsServerEvent {
+    // acpted conn waiting for approve or reject,
+    // sent only if approval is required
+    ConnRequested {
+        conn_index: u64,
+        peer_addr: SocketAddr,
+        // der encoded leaf certificate
+        peer_cert: Option<Vec<u8>>
+    },
+    ConnAccepted {
+        conn_index: u64,
+        peer_info: DtlsPeerInfo
+    },
     SendTimeout {
         conn_index: u64,
         bytes: Bytes
@@ -13,16 +29,39 @@ pub enum DtlsServerEvent {
         conn_index: u64
     },
     Error {
-        err: anyhow::Error
+        err: DtlsError
     },
     ConnError {
         conn_index: u64,
-        err: anyhow::Error
+        err: DtlsError
     },
     ConnClosed {
-        conn_index: u64
+        conn_index: u64,
+        reason: Option<DisconnectReason>
+    },
+    ConnRejected {
+        remote_addr: SocketAddr,
+        reason: DtlsRejectReason
+    },
+    QueueFull {
+        conn_index: u64,
+        // total dropped messages of conn
+        dropped: u64
+    },
+    // conns left while draining, sent when it changes
+    Draining {
+        remaining: usize
+    },
+    // remaining conns are disconnected with ServerShutdown
+    DrainDeadline {
+        remaining: usize
     },
-    ListenerClosed
+    Drained,
+    ListenerClosed,
+    CertReloaded,
+    CertReloadFailed {
+        err: DtlsError
+    }
 }
 
 pub fn timeout_event_system(
@@ -50,6 +89,70 @@ pub fn timeout_event_system(
     }
 }
 
+pub fn reject_event_system(
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    loop {
+        let Some(reject) = dtls_server.reject_check() else {
+            return;
+        };
+
+        dtls_events.send(DtlsServerEvent::ConnRejected { 
+            remote_addr: reject.remote_addr, 
+            reason: reject.reason 
+        });
+    }
+}
+
+pub fn queue_event_system(
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    for full in dtls_server.queue_check() {
+        let conn_index = full.conn_index.index();
+        if full.disconnect {
+            warn!("conn {conn_index} overflowed its queue, disconnecting");
+            dtls_server.disconnect_with_reason(
+                conn_index, 
+                DisconnectReason::QueueFull
+            );
+        }
+
+        dtls_events.send(DtlsServerEvent::QueueFull { 
+            conn_index, 
+            dropped: full.dropped 
+        });
+    }
+}
+
+pub fn drain_event_system(
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    let Some(drain) = dtls_server.drain_check() else {
+        return;
+    };
+
+    match drain {
+        DtlsServerDrain::Remaining(remaining) => {
+            dtls_events.send(DtlsServerEvent::Draining { 
+                remaining 
+            });
+        }
+        DtlsServerDrain::DeadlinePassed(remaining) => {
+            warn!("drain deadline passed, disconnecting {remaining} conns");
+            dtls_server.disconnect_all_with_reason(DisconnectReason::ServerShutdown);
+            dtls_events.send(DtlsServerEvent::DrainDeadline { 
+                remaining 
+            });
+        }
+        DtlsServerDrain::Drained => {
+            dtls_events.send(DtlsServerEvent::Drained);
+        }
+    }
+}
+
 pub fn health_event_system(
     mut dtls_server: ResMut<DtlsServer>,
     mut dtls_events: EventWriter<DtlsServerEvent>
@@ -58,7 +161,7 @@ pub fn health_event_system(
     if let Some(r) = health.listener {
         if let Err(e) = r {
             dtls_events.send(DtlsServerEvent::Error { 
-                err: anyhow!("error from listener: {e}")
+                err: e
             });
         }
 
@@ -69,18 +172,19 @@ pub fn health_event_system(
         if let Some(Err(e)) = conn_health.sender {
             dtls_events.send(DtlsServerEvent::ConnError { 
                 conn_index: conn_health.conn_index.index(), 
-                err: anyhow!("error from sender: {e}")
+                err: e
             });
         }
         if let Some(Err(e)) = conn_health.recver {
             dtls_events.send(DtlsServerEvent::ConnError { 
                 conn_index: conn_health.conn_index.index(), 
-                err: anyhow!("error from recver: {e}")
+                err: e
             });
         }
         if conn_health.closed {
             dtls_events.send(DtlsServerEvent::ConnClosed { 
-                conn_index: conn_health.conn_index.index() 
+                conn_index: conn_health.conn_index.index(),
+                reason: conn_health.reason
             });
         }
     }
@@ -0,0 +1,276 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, ErrorKind},
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::RwLock as StdRwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+use bevy::prelude::*;
+use ipnet::IpNet;
+
+// checked by acpt loop before handshake.
+// deny wins over allow, empty allow admits any ip.
+// ipv4 mapped ipv6 peer is matched by ipv4 nets too
+#[derive(Clone, Debug, Default)]
+pub struct DtlsIpFilter {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+    // bans are loaded on start and saved on each change if some
+    pub ban_file: Option<PathBuf>
+}
+
+impl DtlsIpFilter {
+    #[inline]
+    pub fn is_allowed(&self, ip: IpAddr) -> bool {
+        let canonical = ip.to_canonical();
+        let contains = |n: &IpNet| n.contains(&ip) || n.contains(&canonical);
+        if self.deny.iter()
+        .any(contains) {
+            return false;
+        }
+
+        self.allow.is_empty()
+        || self.allow.iter()
+        .any(contains)
+    }
+}
+
+// shared by acpt loop and server, kept over close() and start().
+// none is permanent, ips are kept canonical
+#[derive(Default, Debug)]
+pub(crate) struct BanList(StdRwLock<HashMap<IpAddr, Option<SystemTime>>>);
+
+impl BanList {
+    pub(crate) fn ban(&self, ip: IpAddr, duration: Option<Duration>) {
+        let until = duration.map(|d| SystemTime::now() + d);
+        self.0.write()
+        .unwrap()
+        .insert(ip.to_canonical(), until);
+    }
+
+    // returns false if ip was not banned
+    pub(crate) fn unban(&self, ip: IpAddr) -> bool {
+        self.0.write()
+        .unwrap()
+        .remove(&ip.to_canonical())
+        .is_some()
+    }
+
+    pub(crate) fn is_banned(&self, ip: IpAddr) -> bool {
+        let bans = self.0.read()
+        .unwrap();
+        match bans.get(&ip.to_canonical()) {
+            Some(Some(until)) => *until > SystemTime::now(),
+            Some(None) => true,
+            None => false
+        }
+    }
+
+    // drops expired bans
+    pub(crate) fn bans(&self) -> Vec<(IpAddr, Option<SystemTime>)> {
+        let now = SystemTime::now();
+        let mut bans = self.0.write()
+        .unwrap();
+        bans.retain(|_, until| !until.is_some_and(|t| t <= now));
+        bans.iter()
+        .map(|(ip, until)| (*ip, *until))
+        .collect()
+    }
+
+    // one ban per line, ip and unix secs of expiry if not permanent.
+    // merged into current bans, missing file is empty
+    pub(crate) fn load(&self, path: &Path) -> io::Result<()> {
+        let text = match fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e)
+        };
+
+        let mut bans = self.0.write()
+        .unwrap();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.split_whitespace();
+            let Some(Ok(ip)) = parts.next()
+            .map(|s| s.parse::<IpAddr>()) else {
+                warn!("invalid line in ban file: {line}");
+                continue;
+            };
+            let until = match parts.next() {
+                Some(s) => match s.parse::<u64>() {
+                    Ok(secs) => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+                    Err(_) => {
+                        warn!("invalid expiry in ban file: {line}");
+                        continue;
+                    }
+                },
+                None => None
+            };
+            bans.insert(ip.to_canonical(), until);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> io::Result<()> {
+        let mut text = String::new();
+        for (ip, until) in self.bans() {
+            match until {
+                Some(t) => {
+                    let secs = t.duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs());
+                    text.push_str(&format!("{ip} {secs}\n"));
+                }
+                None => text.push_str(&format!("{ip}\n"))
+            }
+        }
+
+        // written whole, so that a crash never leaves half of it
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, text)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse()
+        .unwrap()
+    }
+
+    fn net(s: &str) -> IpNet {
+        s.parse()
+        .unwrap()
+    }
+
+    #[test]
+    fn empty_filter_allows_any() {
+        let filter = DtlsIpFilter::default();
+        assert!(filter.is_allowed(ip("10.0.0.1")));
+        assert!(filter.is_allowed(ip("::1")));
+    }
+
+    #[test]
+    fn allow_list() {
+        let filter = DtlsIpFilter{
+            allow: vec![net("10.0.0.0/8")],
+            ..default()
+        };
+        assert!(filter.is_allowed(ip("10.1.2.3")));
+        assert!(!filter.is_allowed(ip("192.168.0.1")));
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let filter = DtlsIpFilter{
+            allow: vec![net("10.0.0.0/8")],
+            deny: vec![net("10.1.0.0/16")],
+            ..default()
+        };
+        assert!(filter.is_allowed(ip("10.2.0.1")));
+        assert!(!filter.is_allowed(ip("10.1.0.1")));
+    }
+
+    #[test]
+    fn mapped_ipv6_matches_ipv4_net() {
+        let filter = DtlsIpFilter{
+            deny: vec![net("10.1.0.0/16")],
+            ..default()
+        };
+        assert!(!filter.is_allowed(ip("::ffff:10.1.0.1")));
+        assert!(filter.is_allowed(ip("::ffff:10.2.0.1")));
+    }
+
+    #[test]
+    fn ban_and_unban() {
+        let bans = BanList::default();
+        bans.ban(ip("10.0.0.1"), None);
+        assert!(bans.is_banned(ip("10.0.0.1")));
+        assert!(!bans.is_banned(ip("10.0.0.2")));
+
+        assert!(bans.unban(ip("10.0.0.1")));
+        assert!(!bans.unban(ip("10.0.0.1")));
+        assert!(!bans.is_banned(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn ban_is_canonical() {
+        let bans = BanList::default();
+        bans.ban(ip("10.0.0.1"), None);
+        assert!(bans.is_banned(ip("::ffff:10.0.0.1")));
+
+        bans.ban(ip("::ffff:10.0.0.2"), None);
+        assert!(bans.is_banned(ip("10.0.0.2")));
+        assert!(bans.unban(ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn expired_ban() {
+        let bans = BanList::default();
+        bans.ban(ip("10.0.0.1"), Some(Duration::ZERO));
+        bans.ban(ip("10.0.0.2"), Some(Duration::from_secs(60)));
+        assert!(!bans.is_banned(ip("10.0.0.1")));
+        assert!(bans.is_banned(ip("10.0.0.2")));
+
+        let listed = bans.bans();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].0, ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir()
+        .join(format!("bevy_dtls_bans_{}.txt", std::process::id()));
+
+        let bans = BanList::default();
+        bans.ban(ip("10.0.0.1"), None);
+        bans.ban(ip("::1"), Some(Duration::from_secs(60)));
+        bans.save(&path)
+        .unwrap();
+
+        let loaded = BanList::default();
+        loaded.load(&path)
+        .unwrap();
+        fs::remove_file(&path)
+        .unwrap();
+
+        assert!(loaded.is_banned(ip("10.0.0.1")));
+        assert!(loaded.is_banned(ip("::1")));
+        let mut listed = loaded.bans();
+        listed.sort_by_key(|(ip, _)| *ip);
+        assert_eq!(listed[0], (ip("10.0.0.1"), None));
+        assert!(listed[1].1.is_some());
+    }
+
+    #[test]
+    fn load_skips_invalid_lines() {
+        let path = std::env::temp_dir()
+        .join(format!("bevy_dtls_bans_invalid_{}.txt", std::process::id()));
+        fs::write(&path, "# comment\n\nnot-an-ip\n10.0.0.1 soon\n10.0.0.2\n")
+        .unwrap();
+
+        let bans = BanList::default();
+        bans.load(&path)
+        .unwrap();
+        fs::remove_file(&path)
+        .unwrap();
+
+        assert_eq!(bans.bans(), vec![(ip("10.0.0.2"), None)]);
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        let bans = BanList::default();
+        bans.load(Path::new("/nonexistent/bevy_dtls_bans.txt"))
+        .unwrap();
+        assert!(bans.bans().is_empty());
+    }
+}
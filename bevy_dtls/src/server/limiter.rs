@@ -0,0 +1,258 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant}
+};
+use bevy::prelude::default;
+
+// limits checked by acpt loop before handshake.
+// none is unlimited, default has every limit off 
+// except handshake timeout, which is always finite
+#[derive(Clone, Debug)]
+pub struct DtlsAcptLimits {
+    // handshakes running at the same time
+    pub max_handshakes: Option<usize>,
+    // handshakes started by one ip in handshake_window_secs
+    pub handshakes_per_ip: Option<u32>,
+    pub handshake_window_secs: u64,
+    // conns of one ip including ones in handshake
+    pub conns_per_ip: Option<usize>,
+    // silent peer is dropped after this
+    pub handshake_timeout_secs: u64
+}
+
+impl Default for DtlsAcptLimits {
+    fn default() -> Self {
+        Self{
+            max_handshakes: None,
+            handshakes_per_ip: None,
+            handshake_window_secs: 10,
+            conns_per_ip: None,
+            handshake_timeout_secs: 10
+        }
+    }
+}
+
+impl DtlsAcptLimits {
+    // handshake flood protection for public servers.
+    // many players behind one nat may need higher handshakes_per_ip
+    pub fn recommended() -> Self {
+        Self{
+            max_handshakes: Some(64),
+            handshakes_per_ip: Some(10),
+            handshake_window_secs: 10,
+            conns_per_ip: None,
+            handshake_timeout_secs: 10
+        }
+    }
+}
+
+// fixed window counter, owned by acpt loop
+pub(crate) struct HandshakeRate {
+    max: Option<u32>,
+    window: Duration,
+    started: HashMap<IpAddr, (Instant, u32)>,
+    last_sweep: Instant
+}
+
+impl HandshakeRate {
+    pub(crate) fn new(limits: &DtlsAcptLimits) -> Self {
+        Self{
+            max: limits.handshakes_per_ip,
+            window: Duration::from_secs(limits.handshake_window_secs),
+            started: default(),
+            last_sweep: Instant::now()
+        }
+    }
+
+    // counts the attempt and returns false if ip exceeded limit
+    pub(crate) fn check(&mut self, ip: IpAddr) -> bool {
+        let Some(max) = self.max else {
+            return true;
+        };
+
+        let now = Instant::now();
+        if now.duration_since(self.last_sweep) >= self.window {
+            let window = self.window;
+            self.started.retain(|_, (start, _)| now.duration_since(*start) < window);
+            self.last_sweep = now;
+        }
+
+        let (start, count) = self.started.entry(ip)
+        .or_insert((now, 0));
+        if now.duration_since(*start) >= self.window {
+            *start = now;
+            *count = 0;
+        }
+
+        *count = count.saturating_add(1);
+        *count <= max
+    }
+}
+
+// conns per ip, shared by acpt loop and server.
+// locked only on accept and removal of conn
+#[derive(Default)]
+pub(crate) struct IpConns(Mutex<HashMap<IpAddr, usize>>);
+
+impl IpConns {
+    pub(crate) fn try_add(&self, ip: IpAddr, max: Option<usize>) -> bool {
+        let mut m = self.0.lock()
+        .unwrap();
+        let n = m.entry(ip)
+        .or_insert(0);
+        if max.is_some_and(|max| *n >= max) {
+            if *n == 0 {
+                m.remove(&ip);
+            }
+            return false;
+        }
+
+        *n += 1;
+        true
+    }
+
+    pub(crate) fn remove(&self, ip: IpAddr) {
+        let mut m = self.0.lock()
+        .unwrap();
+        let Some(n) = m.get_mut(&ip) else {
+            return;
+        };
+
+        *n -= 1;
+        if *n == 0 {
+            m.remove(&ip);
+        }
+    }
+}
+
+// slot taken by IpConns::try_add before handshake,
+// released on drop unless conn is handed to server
+pub(crate) struct IpConnSlot {
+    ip_conns: Arc<IpConns>,
+    ip: IpAddr,
+    kept: bool
+}
+
+impl IpConnSlot {
+    #[inline]
+    pub(crate) fn new(ip_conns: Arc<IpConns>, ip: IpAddr) -> Self {
+        Self{
+            ip_conns,
+            ip,
+            kept: false
+        }
+    }
+
+    // released by server when conn is removed
+    #[inline]
+    pub(crate) fn keep(mut self) {
+        self.kept = true;
+    }
+}
+
+impl Drop for IpConnSlot {
+    fn drop(&mut self) {
+        if !self.kept {
+            self.ip_conns.remove(self.ip);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const OTHER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    fn rate(max: Option<u32>, window_secs: u64) -> HandshakeRate {
+        HandshakeRate::new(&DtlsAcptLimits{
+            handshakes_per_ip: max,
+            handshake_window_secs: window_secs,
+            ..default()
+        })
+    }
+
+    fn count(ip_conns: &IpConns, ip: IpAddr) -> Option<usize> {
+        ip_conns.0.lock()
+        .unwrap()
+        .get(&ip)
+        .copied()
+    }
+
+    #[test]
+    fn handshake_rate_per_ip() {
+        let mut rate = rate(Some(2), 10);
+        assert!(rate.check(IP));
+        assert!(rate.check(IP));
+        assert!(!rate.check(IP));
+        // rejected attempts are counted too
+        assert!(!rate.check(IP));
+        assert!(rate.check(OTHER));
+    }
+
+    #[test]
+    fn handshake_rate_window_resets() {
+        let mut rate = rate(Some(1), 0);
+        assert!(rate.check(IP));
+        assert!(rate.check(IP));
+    }
+
+    #[test]
+    fn handshake_rate_off() {
+        let mut rate = rate(None, 10);
+        for _ in 0..100 {
+            assert!(rate.check(IP));
+        }
+    }
+
+    #[test]
+    fn ip_conns_limit_and_release() {
+        let ip_conns = IpConns::default();
+        assert!(ip_conns.try_add(IP, Some(2)));
+        assert!(ip_conns.try_add(IP, Some(2)));
+        assert!(!ip_conns.try_add(IP, Some(2)));
+        assert!(ip_conns.try_add(OTHER, Some(2)));
+
+        ip_conns.remove(IP);
+        assert!(ip_conns.try_add(IP, Some(2)));
+
+        ip_conns.remove(IP);
+        ip_conns.remove(IP);
+        assert_eq!(count(&ip_conns, IP), None);
+        // never underflows
+        ip_conns.remove(IP);
+        assert_eq!(count(&ip_conns, IP), None);
+    }
+
+    #[test]
+    fn ip_conns_zero_limit_leaves_no_entry() {
+        let ip_conns = IpConns::default();
+        assert!(!ip_conns.try_add(IP, Some(0)));
+        assert_eq!(count(&ip_conns, IP), None);
+    }
+
+    #[test]
+    fn slot_is_released_unless_kept() {
+        let ip_conns = Arc::new(IpConns::default());
+        assert!(ip_conns.try_add(IP, None));
+        drop(IpConnSlot::new(Arc::clone(&ip_conns), IP));
+        assert_eq!(count(&ip_conns, IP), None);
+
+        assert!(ip_conns.try_add(IP, None));
+        IpConnSlot::new(Arc::clone(&ip_conns), IP)
+        .keep();
+        assert_eq!(count(&ip_conns, IP), Some(1));
+    }
+
+    #[test]
+    fn default_limits_are_off_but_handshake_times_out() {
+        let limits = DtlsAcptLimits::default();
+        assert!(limits.max_handshakes.is_none());
+        assert!(limits.handshakes_per_ip.is_none());
+        assert!(limits.conns_per_ip.is_none());
+        assert!(limits.handshake_timeout_secs > 0);
+    }
+}
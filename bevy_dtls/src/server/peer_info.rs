@@ -0,0 +1,68 @@
+use std::net::{IpAddr, SocketAddr};
+use webrtc_dtls::conn::DTLSConn;
+use x509_parser::{
+    certificate::X509Certificate, 
+    extensions::GeneralName, 
+    prelude::FromDer
+};
+
+#[derive(Clone, Debug)]
+pub struct DtlsPeerInfo {
+    pub remote_addr: SocketAddr,
+    // der encoded, leaf first
+    pub certificates: Vec<Vec<u8>>,
+    pub subject: Option<String>,
+    pub subject_alt_names: Vec<String>,
+    // identity sent by client in psk mode
+    pub psk_identity: Option<Vec<u8>>
+}
+
+impl DtlsPeerInfo {
+    pub(crate) async fn from_conn(
+        dtls_conn: &DTLSConn, 
+        remote_addr: SocketAddr
+    ) -> Self {
+        let state = dtls_conn.connection_state()
+        .await;
+
+        let mut info = Self{
+            remote_addr,
+            certificates: state.peer_certificates,
+            subject: None,
+            subject_alt_names: vec![],
+            psk_identity: if state.identity_hint.is_empty() {
+                None
+            } else {
+                Some(state.identity_hint)
+            }
+        };
+
+        if let Some(leaf) = info.certificates.first() {
+            if let Ok((_, cert)) = X509Certificate::from_der(leaf) {
+                info.subject = Some(cert.subject().to_string());
+                if let Ok(Some(san)) = cert.subject_alternative_name() {
+                    info.subject_alt_names = san.value.general_names
+                    .iter()
+                    .map(general_name_to_string)
+                    .collect();
+                }
+            }
+        }
+
+        info
+    }
+}
+
+fn general_name_to_string(name: &GeneralName) -> String {
+    match name {
+        GeneralName::DNSName(s) => s.to_string(),
+        GeneralName::IPAddress(b) => {
+            match b.len() {
+                4 => IpAddr::from(<[u8; 4]>::try_from(*b).unwrap()).to_string(),
+                16 => IpAddr::from(<[u8; 16]>::try_from(*b).unwrap()).to_string(),
+                _ => name.to_string()
+            }
+        }
+        _ => name.to_string()
+    }
+}
@@ -1,6 +1,6 @@
-use anyhow::anyhow;
 use bevy::prelude::*;
 use rustls::crypto::aws_lc_rs;
+use crate::queue::DtlsQueueConfig;
 use super::{
     dtls_server::DtlsServer, 
     event::{self, DtlsServerEvent}
@@ -8,26 +8,43 @@ use super::{
 
 fn accept_system(
     mut dtls_server: ResMut<DtlsServer>,
-    mut errors: EventWriter<DtlsServerEvent>
+    mut dtls_events: EventWriter<DtlsServerEvent>
 ) {
     if dtls_server.is_closed() {
         return;
     }
     
-    loop {
-        let Some(conn_idx) = dtls_server.acpt() else {
-            return;
-        };
-    
-        if let Err(e) = dtls_server.start_conn(conn_idx) {
-            errors.send(DtlsServerEvent::Error { 
-                err: anyhow!("conn {conn_idx:?} could not be started: {e}") 
-            });
-
+    while let Some(conn_idx) = dtls_server.acpt() {
+        if dtls_server.require_approval() {
+            if let Some(peer_info) = dtls_server.peer_info(conn_idx.index()) {
+                dtls_events.send(DtlsServerEvent::ConnRequested { 
+                    conn_index: conn_idx.index(), 
+                    peer_addr: peer_info.remote_addr, 
+                    peer_cert: peer_info.certificates
+                    .into_iter()
+                    .next()
+                });
+            }
             continue;
         }
-    
+
+        if let Err(e) = dtls_server.approve(conn_idx.index()) {
+            warn!("conn {conn_idx:?} could not be started: {e}");
+            dtls_events.send(DtlsServerEvent::Error { 
+                err: e 
+            });
+        }
+    }
+
+    while let Some(conn_idx) = dtls_server.approve_check() {
         debug!("conn {conn_idx:?} has been started from default system");
+
+        if let Some(peer_info) = dtls_server.peer_info(conn_idx.index()) {
+            dtls_events.send(DtlsServerEvent::ConnAccepted { 
+                conn_index: conn_idx.index(), 
+                peer_info 
+            });
+        }
     }
 }
 
@@ -35,7 +52,15 @@ pub struct DtlsServerPlugin {
     pub max_clients: usize,
     pub buf_size: usize,
     pub send_timeout_secs: u64,
-    pub recv_timeout_secs: Option<u64>
+    pub recv_timeout_secs: Option<u64>,
+    pub queue: DtlsQueueConfig,
+    // measures rtt and loss of each conn, see conn_stats
+    pub ping_secs: Option<u64>,
+    // acpted conns are reported as ConnRequested
+    // and started by DtlsServer::approve if true
+    pub require_approval: bool,
+    // pending conns are rejected with ApprovalTimeout after this if some
+    pub pending_timeout_secs: Option<u64>
 }
 
 impl Plugin for DtlsServerPlugin {
@@ -46,22 +71,29 @@ impl Plugin for DtlsServerPlugin {
             panic!("failed to setup crypto provider");
         }
 
-        let dtls_server = match DtlsServer::new(
+        let mut dtls_server = match DtlsServer::new(
             self.max_clients,
             self.buf_size, 
             self.send_timeout_secs,
-            self.recv_timeout_secs
+            self.recv_timeout_secs,
+            self.queue,
+            self.ping_secs
         ) {
             Ok(s) => s,
             Err(e) => panic!("{e}")
         };
+        dtls_server.set_require_approval(self.require_approval);
+        dtls_server.set_pending_timeout_secs(self.pending_timeout_secs);
 
         app.insert_resource(dtls_server)
         .add_event::<DtlsServerEvent>()
         .add_systems(PreUpdate, accept_system)
         .add_systems(PostUpdate, (
             event::health_event_system,
-            event::timeout_event_system
+            event::timeout_event_system,
+            event::reject_event_system,
+            event::queue_event_system,
+            event::drain_event_system
         ).chain());
     }
 }
@@ -0,0 +1,178 @@
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant}
+};
+use crate::ping::PingTracker;
+
+// snapshot of a conn, or of all conns on server
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DtlsConnStats {
+    pub bytes_sent: u64,
+    pub packets_sent: u64,
+    pub bytes_received: u64,
+    pub packets_received: u64,
+    pub send_timeouts: u64,
+    pub recv_timeouts: u64,
+    pub dropped_packets: u64,
+    pub last_received: Option<Instant>,
+    pub age: Duration,
+    // measured by ping, none if ping is disabled or not answered yet
+    pub rtt: Option<Duration>,
+    pub jitter: Option<Duration>,
+    // ratio of pings without pong, 0.0 to 1.0
+    pub packet_loss: Option<f32>
+}
+
+impl DtlsConnStats {
+    #[inline]
+    pub fn since_last_received(&self) -> Option<Duration> {
+        self.last_received
+        .map(|t| t.elapsed())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DtlsServerStats {
+    pub connections: usize,
+    // sum of live conns.
+    // rtt, jitter and loss are per conn only and left none
+    pub live: DtlsConnStats,
+    // sum of all conns since server is created, including closed
+    pub lifetime: DtlsConnStats,
+    pub accepted: u64,
+    pub rejected_max_clients: u64,
+    pub handshake_failures: u64,
+    // rejected by DtlsAcptLimits before handshake
+    pub rejected_handshakes_full: u64,
+    pub rejected_handshake_rate: u64,
+    pub rejected_conns_per_ip: u64,
+    // rejected by allow and deny lists or bans
+    pub rejected_ip_filter: u64,
+    pub rejected_draining: u64
+}
+
+// server wide counters shared with acpter loop
+#[derive(Default, Debug)]
+pub(crate) struct ServerCounters {
+    // accepted and not removed yet, including ones waiting for acpt()
+    pub(crate) conns: AtomicUsize,
+    pub(crate) accepted: AtomicU64,
+    pub(crate) rejected_max_clients: AtomicU64,
+    pub(crate) handshake_failures: AtomicU64,
+    pub(crate) rejected_handshakes_full: AtomicU64,
+    pub(crate) rejected_handshake_rate: AtomicU64,
+    pub(crate) rejected_conns_per_ip: AtomicU64,
+    pub(crate) rejected_ip_filter: AtomicU64,
+    pub(crate) rejected_draining: AtomicU64
+}
+
+impl ServerCounters {
+    #[inline]
+    pub(crate) fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+// updated by loops, read by ecs
+#[derive(Debug)]
+pub(crate) struct StatsCounter {
+    created: Instant,
+    bytes_sent: AtomicU64,
+    packets_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    packets_received: AtomicU64,
+    send_timeouts: AtomicU64,
+    recv_timeouts: AtomicU64,
+    // nanos since created, 0 is never received
+    last_received: AtomicU64,
+    pub(crate) ping: PingTracker
+}
+
+impl Default for StatsCounter {
+    fn default() -> Self {
+        Self{
+            created: Instant::now(),
+            bytes_sent: AtomicU64::new(0),
+            packets_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            packets_received: AtomicU64::new(0),
+            send_timeouts: AtomicU64::new(0),
+            recv_timeouts: AtomicU64::new(0),
+            last_received: AtomicU64::new(0),
+            ping: PingTracker::default()
+        }
+    }
+}
+
+impl StatsCounter {
+    #[inline]
+    pub(crate) fn on_sent(&self, n: usize) {
+        self.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn on_received(&self, n: usize) {
+        self.bytes_received.fetch_add(n as u64, Ordering::Relaxed);
+        self.packets_received.fetch_add(1, Ordering::Relaxed);
+        let nanos = self.created.elapsed()
+        .as_nanos()
+        .clamp(1, u64::MAX as u128) as u64;
+        self.last_received.store(nanos, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn on_send_timeout(&self) {
+        self.send_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn on_recv_timeout(&self) {
+        self.recv_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, dropped_packets: u64) -> DtlsConnStats {
+        let last_received = match self.last_received.load(Ordering::Relaxed) {
+            0 => None,
+            n => Some(self.created + Duration::from_nanos(n))
+        };
+        let (rtt, jitter, packet_loss) = self.ping.snapshot();
+
+        DtlsConnStats{
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            packets_sent: self.packets_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            packets_received: self.packets_received.load(Ordering::Relaxed),
+            send_timeouts: self.send_timeouts.load(Ordering::Relaxed),
+            recv_timeouts: self.recv_timeouts.load(Ordering::Relaxed),
+            dropped_packets,
+            last_received,
+            age: self.created.elapsed(),
+            rtt,
+            jitter,
+            packet_loss
+        }
+    }
+}
+
+impl DtlsConnStats {
+    pub(crate) fn accumulate(&mut self, conn: &DtlsConnStats) {
+        self.bytes_sent += conn.bytes_sent;
+        self.packets_sent += conn.packets_sent;
+        self.bytes_received += conn.bytes_received;
+        self.packets_received += conn.packets_received;
+        self.send_timeouts += conn.send_timeouts;
+        self.recv_timeouts += conn.recv_timeouts;
+        self.dropped_packets += conn.dropped_packets;
+        self.last_received = self.last_received.max(conn.last_received);
+        self.age = self.age.max(conn.age);
+    }
+}
+
+impl DtlsServerStats {
+    pub(crate) fn add(&mut self, conn: &DtlsConnStats) {
+        self.connections += 1;
+        self.live.accumulate(conn);
+        self.lifetime.accumulate(conn);
+    }
+}
@@ -0,0 +1,68 @@
+use std::time::Duration;
+use webrtc_dtls::{
+    cipher_suite::CipherSuiteId, 
+    config::{Config, ExtendedMasterSecretType}, 
+    signature_hash_algorithm::SignatureScheme
+};
+
+// dtls options applied on top of config built by cert options.
+// empty or zero value leaves webrtc-dtls default.
+// key exchange curves are not configurable in webrtc-dtls
+#[derive(Clone)]
+pub struct DtlsTuning {
+    pub extended_master_secret: ExtendedMasterSecretType,
+    pub cipher_suites: Vec<CipherSuiteId>,
+    pub signature_schemes: Vec<SignatureScheme>,
+    pub mtu: usize,
+    pub flight_interval: Duration,
+    pub replay_protection_window: usize
+}
+
+impl Default for DtlsTuning {
+    fn default() -> Self {
+        Self{
+            extended_master_secret: ExtendedMasterSecretType::Require,
+            cipher_suites: vec![],
+            signature_schemes: vec![],
+            mtu: 0,
+            flight_interval: Duration::ZERO,
+            replay_protection_window: 0
+        }
+    }
+}
+
+impl DtlsTuning {
+    // restricts cipher suites to AEAD (GCM and CCM)
+    pub fn aead_only() -> Self {
+        Self{
+            cipher_suites: vec![
+                CipherSuiteId::Tls_Ecdhe_Ecdsa_With_Aes_128_Gcm_Sha256,
+                CipherSuiteId::Tls_Ecdhe_Ecdsa_With_Aes_128_Ccm,
+                CipherSuiteId::Tls_Ecdhe_Ecdsa_With_Aes_128_Ccm_8,
+                CipherSuiteId::Tls_Psk_With_Aes_128_Gcm_Sha256,
+                CipherSuiteId::Tls_Psk_With_Aes_128_Ccm,
+                CipherSuiteId::Tls_Psk_With_Aes_128_Ccm_8
+            ],
+            ..Default::default()
+        }
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        config.extended_master_secret = self.extended_master_secret;
+        if !self.cipher_suites.is_empty() {
+            config.cipher_suites.clone_from(&self.cipher_suites);
+        }
+        if !self.signature_schemes.is_empty() {
+            config.signature_schemes.clone_from(&self.signature_schemes);
+        }
+        if self.mtu != 0 {
+            config.mtu = self.mtu;
+        }
+        if !self.flight_interval.is_zero() {
+            config.flight_interval = self.flight_interval;
+        }
+        if self.replay_protection_window != 0 {
+            config.replay_protection_window = self.replay_protection_window;
+        }
+    }
+}
@@ -20,7 +20,7 @@ fn send_hellooon_system(
     mut dtls_client: ResMut<DtlsClient>,
     mut counter: ResMut<ClientHellooonCounter>
 ) {
-    if renet_client.is_disconnected() {
+    if !renet_client.is_connected() {
         return;
     }
 
@@ -60,14 +60,30 @@ fn handle_net_event(
 ) {
     for e in dtls_events.read() {
         match e {
+            DtlsClientEvent::Connecting => {
+                info!("connecting...");
+            }
+            DtlsClientEvent::Connected => {
+                info!("connected");
+            }
+            DtlsClientEvent::ConnectFailed { err } => {
+                warn!("{err}");
+                restart.0 = true;
+            }
             DtlsClientEvent::SendTimeout { .. } => {
                 error!("sending timeout")
             }
+            DtlsClientEvent::RecvTimeout => {
+                error!("receiving timeout")
+            }
+            DtlsClientEvent::QueueFull { dropped } => {
+                warn!("queue is full, dropped: {dropped}");
+            }
+            // this demo restarts by itself instead of reconnect policy
+            DtlsClientEvent::Reconnecting { .. } 
+            | DtlsClientEvent::ReconnectGaveUp { .. } => (),
             DtlsClientEvent::Error { err } => {
-                if err.to_string()
-                .ends_with("Alert is Fatal or Close Notify")
-                || err.to_string()
-                .ends_with("conn is closed") {
+                if err.is_disconnect() {
                     info!("server disconneted: {err}");
                 } else {
                     error!("{err:?}");
@@ -77,7 +93,8 @@ fn handle_net_event(
                     renet.disconnect_dtls(&mut dtls_client);
                 }
             }
-            DtlsClientEvent::ConnClosed => {
+            DtlsClientEvent::ConnClosed { reason } => {
+                info!("conn closed with reason {reason:?}");
                 // this event can be emitted even before disconnect() is called
                 // just make sure close before restart
                 if let Some(ref mut renet) = renet_client {
@@ -140,7 +157,8 @@ impl Plugin for ClientPlugin {
             cert_option: ClientCertOption::Load { 
                 server_name: "webrtc.rs",
                 root_ca_path: "my_certificates/server.pub.pem" 
-            }
+            },
+            tuning: default()
         });
 
         if let Err(e) = renet_client.start_dtls(
@@ -177,7 +195,10 @@ fn main() {
         RenetClientPlugin,
         RenetDtlsClientPlugin{
             timeout_secs: 5,
-            buf_size: 1500
+            buf_size: 1500,
+            recv_timeout_secs: None,
+            heartbeat_secs: None,
+            queue: default()
         }
     ))
     .add_plugins(ClientPlugin)
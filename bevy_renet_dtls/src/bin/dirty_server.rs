@@ -11,10 +11,13 @@ use bevy_renet::{
     renet::{ConnectionConfig, DefaultChannel, RenetServer}, 
     RenetServerPlugin
 };
-use bevy_dtls::server::{
-    cert_option::ServerCertOption, 
-    dtls_server::{DtlsServer, DtlsServerConfig}, 
-    event::DtlsServerEvent
+use bevy_dtls::{
+    disconnect::DisconnectReason, 
+    server::{
+        cert_option::ServerCertOption, 
+        dtls_server::{DtlsServer, DtlsServerConfig}, 
+        event::DtlsServerEvent
+    }
 };
 use bevy_renet_dtls::server::{RenetDtlsServerPlugin, RenetServerDtlsExt};
 use bytes::Bytes;
@@ -40,7 +43,10 @@ fn send_hellooon_system(
     if counter.0 % 100 == 0 {
         info!("disconnecting all...");
         // disconnect all
-        renet_server.disconnect_all_dtls(&mut dtls_server);
+        renet_server.disconnect_all_dtls_with_reason(
+            &mut dtls_server, 
+            DisconnectReason::ServerShutdown
+        );
         counter.0 = 0;
         // close listener(accepter)
         dtls_server.close();
@@ -73,6 +79,16 @@ fn handle_net_event(
 ) {
     for e in dtls_events.read() {
         match e {
+            DtlsServerEvent::ConnRequested { conn_index, peer_addr, .. } => {
+                info!("conn {conn_index} requested from {peer_addr}");
+                if let Err(e) = dtls_server.approve(*conn_index) {
+                    error!("could not approve conn {conn_index}: {e}");
+                    dtls_server.reject(*conn_index, None);
+                }
+            }
+            DtlsServerEvent::ConnAccepted { conn_index, peer_info } => {
+                info!("conn {conn_index} accepted from {}", peer_info.remote_addr);
+            }
             DtlsServerEvent::SendTimeout { conn_index, .. } => {
                 error!("conn {conn_index} sending timeout");
             }
@@ -84,24 +100,36 @@ fn handle_net_event(
                 error!("{err}");
             }
             DtlsServerEvent::ConnError { conn_index, err } => {
-                // better way to get this specific error ??
-                if err.to_string()
-                .ends_with("Alert is Fatal or Close Notify")
-                || err.to_string()
-                .ends_with("conn is closed") {
+                if err.is_disconnect() {
                     info!("client {conn_index} disconnected: {err}");
                 } else {
                     error!("client {conn_index} error: {err}: disconnecting");
                 }
                 renet_server.disconnect_dtls(&mut dtls_server, *conn_index);
             }
-            DtlsServerEvent::ConnClosed { conn_index } => {
+            DtlsServerEvent::ConnClosed { conn_index, reason } => {
                 info!(
-                    "conn {conn_index} closed, current clients: {} (transport: {})",
+                    "conn {conn_index} closed with reason {reason:?}, \
+                    current clients: {} (transport: {})",
                     renet_server.connected_clients(),
                     dtls_server.connected_clients()
                 );
             }
+            DtlsServerEvent::ConnRejected { remote_addr, reason } => {
+                warn!("{remote_addr} is rejected: {reason:?}");
+            }
+            DtlsServerEvent::QueueFull { conn_index, dropped } => {
+                warn!("conn {conn_index} queue is full, dropped: {dropped}");
+            }
+            DtlsServerEvent::Draining { remaining } => {
+                info!("draining, remaining conns: {remaining}");
+            }
+            DtlsServerEvent::DrainDeadline { remaining } => {
+                warn!("drain deadline passed, disconnected {remaining} conns");
+            }
+            DtlsServerEvent::Drained => {
+                info!("drained");
+            }
             DtlsServerEvent::ListenerClosed => {
                 // this event can be emitted even while conns are alive 
                 // just make sure close all again before restart
@@ -112,6 +140,12 @@ fn handle_net_event(
 
                 restart.0 = true;                
             }
+            DtlsServerEvent::CertReloaded => {
+                info!("certificates are reloaded");
+            }
+            DtlsServerEvent::CertReloadFailed { err } => {
+                error!("{err}");
+            }
         }
     }
 }
@@ -158,7 +192,10 @@ impl Plugin for ServerPlugin {
             cert_option: ServerCertOption::Load { 
                 priv_key_path: "my_certificates/server.priv.pem", 
                 certificate_path: "my_certificates/server.pub.pem",
-            }
+            },
+            tuning: default(),
+            limits: default(),
+            ip_filter: default()
         });
 
         if let Err(e) = dtls_server.start(server_config.0.clone()) {
@@ -198,7 +235,11 @@ fn main() {
             max_clients: 10,
             buf_size: 1500,
             send_timeout_secs: 1,
-            recv_timeout_secs: Some(1)
+            recv_timeout_secs: Some(1),
+            queue: default(),
+            require_approval: true,
+            pending_timeout_secs: Some(10),
+            token: None
         }
     ))
     .add_plugins(ServerPlugin)
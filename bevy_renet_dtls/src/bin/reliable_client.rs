@@ -67,7 +67,8 @@ impl Plugin for ClientPlugin {
                 server_port: self.server_port,
                 client_addr: self.client_addr,
                 client_port: self.client_port,
-                cert_option: self.cert_option,
+                cert_option: self.cert_option.clone(),
+                tuning: default()
             }
         ) {
             panic!("{e}");
@@ -95,7 +96,10 @@ fn main() {
         RenetClientPlugin,
         RenetDtlsClientPlugin{
             timeout_secs: 10,
-            buf_size: 512
+            buf_size: 512,
+            recv_timeout_secs: None,
+            heartbeat_secs: None,
+            queue: default()
         }
     ))
     .add_plugins(
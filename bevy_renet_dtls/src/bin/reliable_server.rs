@@ -72,7 +72,10 @@ impl Plugin for ServerPlugin {
         if let Err(e) = dtls_server.start(DtlsServerConfig{
             listen_addr: self.listen_addr,
             listen_port: self.listen_port,
-            cert_option: self.cert_option
+            cert_option: self.cert_option.clone(),
+            tuning: default(),
+            limits: default(),
+            ip_filter: default()
         }) {
             panic!("{e}");
         }
@@ -109,7 +112,11 @@ fn main() {
             max_clients: 1,
             buf_size: 512,
             send_timeout_secs: 10,
-            recv_timeout_secs: None
+            recv_timeout_secs: None,
+            queue: default(),
+            require_approval: false,
+            pending_timeout_secs: Some(10),
+            token: None
         }
     ))
     .add_plugins(ServerPlugin{
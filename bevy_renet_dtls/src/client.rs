@@ -1,25 +1,47 @@
-use anyhow::anyhow;
+use std::time::{Duration, Instant};
 use bevy::prelude::*;
 use bevy_renet::{renet::RenetClient, RenetReceive, RenetSend};
-use bevy_dtls::client::{
-    dtls_client::{DtlsClient, DtlsClientConfig}, 
-    event::{self, DtlsClientEvent}
+use bevy_dtls::{
+    client::{
+        dtls_client::{
+            DtlsClient, 
+            DtlsClientConfig, 
+            DtlsClientConnect, 
+            DtlsClientState
+        }, 
+        event::{self, DtlsClientEvent}
+    }, 
+    disconnect::DisconnectReason, 
+    error::DtlsError, 
+    queue::DtlsQueueConfig
 };
 use bytes::Bytes;
 use rustls::crypto::aws_lc_rs;
-use crate::DtlsSet;
+use crate::{
+    token::{self, DtlsClientToken}, 
+    DtlsSet
+};
+
+// token is sent again until server acks it
+const TOKEN_RESEND_INTERVAL: Duration = Duration::from_millis(250);
 
 pub trait RenetClientDtlsExt {
     fn start_dtls(
         &mut self,
         dtls_client: &mut DtlsClient,
         config: DtlsClientConfig
-    ) -> anyhow::Result<()>;
+    ) -> Result<(), DtlsError>;
 
     fn disconnect_dtls(
         &mut self,
         dtls_client: &mut DtlsClient
     );
+
+    fn disconnect_dtls_with_reason(
+        &mut self,
+        dtls_client: &mut DtlsClient,
+        reason: DisconnectReason
+    );
 }
 
 impl RenetClientDtlsExt for RenetClient {
@@ -28,29 +50,95 @@ impl RenetClientDtlsExt for RenetClient {
         &mut self,
         dtls_client: &mut DtlsClient, 
         config: DtlsClientConfig
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), DtlsError> {
+        // set connected by connect system
         self.set_connecting();
-        dtls_client.start(config)?;
-        self.set_connected();
-        Ok(())
+        dtls_client.start(config)
     }
 
     #[inline]
     fn disconnect_dtls(
         &mut self,
         dtls_client: &mut DtlsClient
+    ) {
+        self.disconnect_dtls_with_reason(
+            dtls_client, 
+            DisconnectReason::Disconnected
+        );
+    }
+
+    #[inline]
+    fn disconnect_dtls_with_reason(
+        &mut self,
+        dtls_client: &mut DtlsClient,
+        reason: DisconnectReason
     ) {
         self.disconnect();
-        dtls_client.disconnect();    
+        dtls_client.disconnect_with_reason(reason);    
+    }
+}
+
+fn connect_system(
+    mut renet_client: ResMut<RenetClient>,
+    mut dtls_client: ResMut<DtlsClient>,
+    mut client_token: Option<ResMut<DtlsClientToken>>,
+    mut dtls_events: EventWriter<DtlsClientEvent>
+) {
+    loop {
+        let Some(c) = dtls_client.connect_check() else {
+            return;
+        };
+
+        match c {
+            DtlsClientConnect::Connecting => {
+                dtls_events.send(DtlsClientEvent::Connecting);
+            }
+            DtlsClientConnect::Connected => {
+                // set connected when token is acked
+                match client_token {
+                    Some(ref mut t) => t.reset(),
+                    None => renet_client.set_connected()
+                }
+                dtls_events.send(DtlsClientEvent::Connected);
+            }
+            DtlsClientConnect::Failed(err) => {
+                renet_client.disconnect_due_to_transport();
+                dtls_events.send(DtlsClientEvent::ConnectFailed { 
+                    err 
+                });
+            }
+        }
+    }
+}
+
+fn token_system(
+    dtls_client: Res<DtlsClient>,
+    mut client_token: ResMut<DtlsClientToken>,
+    mut errors: EventWriter<DtlsClientEvent>
+) {
+    if dtls_client.state() != DtlsClientState::Connected
+    || client_token.accepted
+    || client_token.last_sent
+    .is_some_and(|t| t.elapsed() < TOKEN_RESEND_INTERVAL) {
+        return;
+    }
+
+    client_token.last_sent = Some(Instant::now());
+    if let Err(e) = dtls_client.send(Bytes::copy_from_slice(client_token.bytes())) {
+        errors.send(DtlsClientEvent::Error { 
+            err: e 
+        });
     }
 }
 
 fn send_system(
     mut renet_client: ResMut<RenetClient>,
     dtls_client: Res<DtlsClient>,
+    client_token: Option<Res<DtlsClientToken>>,
     mut errors: EventWriter<DtlsClientEvent>
 ) {
-    if dtls_client.is_closed() {
+    if dtls_client.state() != DtlsClientState::Connected
+    || client_token.is_some_and(|t| !t.accepted) {
         return;
     }
 
@@ -60,7 +148,7 @@ fn send_system(
     for pkt in packets {
         if let Err(e) = dtls_client.send(Bytes::from(pkt)) {
             errors.send(DtlsClientEvent::Error { 
-                err: anyhow!("error on sending: {e}") 
+                err: e 
             });
 
             break;
@@ -70,7 +158,8 @@ fn send_system(
 
 fn recv_system(
     mut renet_client: ResMut<RenetClient>,
-    mut dtls_client: ResMut<DtlsClient>
+    mut dtls_client: ResMut<DtlsClient>,
+    mut client_token: Option<ResMut<DtlsClientToken>>
 ) {
     if dtls_client.is_closed() {
         return;
@@ -81,13 +170,50 @@ fn recv_system(
             return;
         };
 
+        if let Some(ref mut t) = client_token {
+            if token::is_token_ack(&bytes) {
+                if !t.accepted {
+                    t.accepted = true;
+                    renet_client.set_connected();
+                    debug!("connect token is accepted");
+                }
+                continue;
+            }
+        }
+
         renet_client.process_packet(&bytes);
     }
 }
 
+fn queue_system(
+    mut renet_client: ResMut<RenetClient>,
+    mut dtls_client: ResMut<DtlsClient>,
+    mut dtls_events: EventWriter<DtlsClientEvent>
+) {
+    let Some(full) = dtls_client.queue_check() else {
+        return;
+    };
+
+    if full.disconnect {
+        warn!("dtls client overflowed its queue, disconnecting");
+        renet_client.disconnect_dtls_with_reason(
+            &mut dtls_client, 
+            DisconnectReason::QueueFull
+        );
+    }
+
+    dtls_events.send(DtlsClientEvent::QueueFull { 
+        dropped: full.dropped 
+    });
+}
+
 pub struct RenetDtlsClientPlugin {
     pub timeout_secs: u64,
-    pub buf_size: usize
+    pub buf_size: usize,
+    pub recv_timeout_secs: Option<u64>,
+    // sends keepalive while idle so that server recv timeout won't fire
+    pub heartbeat_secs: Option<u64>,
+    pub queue: DtlsQueueConfig
 }
 
 impl Plugin for RenetDtlsClientPlugin {
@@ -98,7 +224,15 @@ impl Plugin for RenetDtlsClientPlugin {
             info!("crypto provider already exists");
         }
 
-        let dtls_client = match DtlsClient::new(self.buf_size, self.timeout_secs) {
+        let dtls_client = match DtlsClient::new(
+            self.buf_size, 
+            self.timeout_secs,
+            self.recv_timeout_secs,
+            self.heartbeat_secs,
+            self.queue,
+            // renet measures rtt by itself
+            None
+        ) {
             Ok(c) => c,
             Err(e) => panic!("{e}")
         };
@@ -107,6 +241,11 @@ impl Plugin for RenetDtlsClientPlugin {
         .add_event::<DtlsClientEvent>()
         .configure_sets(PreUpdate, DtlsSet::Recv.before(RenetReceive))
         .configure_sets(PostUpdate, DtlsSet::Send.after(RenetSend))
+        .add_systems(PreUpdate, 
+            connect_system
+            .before(DtlsSet::Recv)
+            .run_if(resource_exists::<RenetClient>)
+        )
         .add_systems(PreUpdate, 
             recv_system
             .in_set(DtlsSet::Recv)
@@ -117,9 +256,16 @@ impl Plugin for RenetDtlsClientPlugin {
             .in_set(DtlsSet::Send)
             .run_if(resource_exists::<RenetClient>)
         )
+        .add_systems(PostUpdate, 
+            token_system
+            .in_set(DtlsSet::Send)
+            .run_if(resource_exists::<DtlsClientToken>)
+        )
         .add_systems(PostUpdate, (
             event::health_event_system,
-            event::timeout_event_system
+            event::timeout_event_system,
+            queue_system
+            .run_if(resource_exists::<RenetClient>)
         )
             .chain()
             .after(DtlsSet::Send)
@@ -1,5 +1,6 @@
 pub mod server;
 pub mod client;
+pub mod token;
 
 pub use bevy_dtls as dtls;
 use bevy::prelude::SystemSet;
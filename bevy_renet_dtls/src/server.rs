@@ -1,13 +1,25 @@
-use anyhow::anyhow;
+use std::{
+    net::IpAddr,
+    time::{Duration, Instant}
+};
 use bevy::prelude::*;
 use bevy_renet::{renet::{ClientId, RenetServer}, RenetReceive, RenetSend};
-use bevy_dtls::server::{
-    dtls_server::DtlsServer, 
-    event::{self, DtlsServerEvent}
+use bevy_dtls::{
+    disconnect::DisconnectReason, 
+    error::DtlsError, 
+    queue::DtlsQueueConfig, 
+    server::{
+        dtls_server::{DtlsServer, DtlsServerDrain}, 
+        event::{self, DtlsServerEvent}
+    }
 };
 use bytes::Bytes;
 use rustls::crypto::aws_lc_rs;
-use crate::{ConnIndexRenetExt, DtlsSet};
+use crate::{
+    token::{self, DtlsTokenAuth, DtlsTokenConfig, DtlsTokenError, DtlsTokenEvent}, 
+    ConnIndexRenetExt, 
+    DtlsSet
+};
 
 pub trait RenetServerDtlsExt {
     fn disconnect_dtls(
@@ -16,7 +28,27 @@ pub trait RenetServerDtlsExt {
         conn_index: u64
     );
 
+    fn disconnect_dtls_with_reason(
+        &mut self, 
+        dtls_server: &mut DtlsServer, 
+        conn_index: u64,
+        reason: DisconnectReason
+    );
+
     fn disconnect_all_dtls(&mut self, dtls_server: &mut DtlsServer);
+
+    fn disconnect_all_dtls_with_reason(
+        &mut self, 
+        dtls_server: &mut DtlsServer,
+        reason: DisconnectReason
+    );
+
+    fn ban_dtls(
+        &mut self, 
+        dtls_server: &mut DtlsServer, 
+        addr: IpAddr, 
+        duration: Option<Duration>
+    ) -> Result<(), DtlsError>;
 }
 
 impl RenetServerDtlsExt for RenetServer {
@@ -25,53 +57,128 @@ impl RenetServerDtlsExt for RenetServer {
         &mut self, 
         dtls_server: &mut DtlsServer, 
         conn_index: u64
+    ) {
+        self.disconnect_dtls_with_reason(
+            dtls_server, 
+            conn_index, 
+            DisconnectReason::Disconnected
+        );
+    }
+
+    #[inline]
+    fn disconnect_dtls_with_reason(
+        &mut self, 
+        dtls_server: &mut DtlsServer, 
+        conn_index: u64,
+        reason: DisconnectReason
     ) {
         let client_id = ClientId::from_raw(conn_index);
         self.disconnect(client_id);
-        dtls_server.disconnect(conn_index);
+        dtls_server.disconnect_with_reason(conn_index, reason);
         self.remove_connection(client_id);
     }
 
+    #[inline]
     fn disconnect_all_dtls(&mut self, dtls_server: &mut DtlsServer) {
+        self.disconnect_all_dtls_with_reason(
+            dtls_server, 
+            DisconnectReason::Disconnected
+        );
+    }
+
+    fn disconnect_all_dtls_with_reason(
+        &mut self, 
+        dtls_server: &mut DtlsServer,
+        reason: DisconnectReason
+    ) {
         let indices = dtls_server.client_indices();
         for idx in indices {
-            self.disconnect_dtls(dtls_server, idx);         
+            self.disconnect_dtls_with_reason(dtls_server, idx, reason);         
         }
     }
+
+    // renet clients of ip are removed in the same frame
+    fn ban_dtls(
+        &mut self, 
+        dtls_server: &mut DtlsServer, 
+        addr: IpAddr, 
+        duration: Option<Duration>
+    ) -> Result<(), DtlsError> {
+        let client_ids = dtls_server.conns_of_ip(addr)
+        .into_iter()
+        .map(ClientId::from_raw)
+        .collect::<Vec<_>>();
+        for client_id in client_ids.iter() {
+            self.disconnect(*client_id);
+        }
+
+        let result = dtls_server.ban(addr, duration);
+        for client_id in client_ids {
+            self.remove_connection(client_id);
+        }
+        result
+    }
 }
 
 fn acpt_system(
     mut renet_server: ResMut<RenetServer>,
     mut dtls_server: ResMut<DtlsServer>,
+    mut token_auth: Option<ResMut<DtlsTokenAuth>>,
     mut errors: EventWriter<DtlsServerEvent>
 ) {
     if dtls_server.is_closed() {
         return;
     }
 
-    loop {
-        let Some(conn_idx) = dtls_server.acpt() else {
-            return;
-        };
+    while let Some(conn_idx) = dtls_server.acpt() {
+        if dtls_server.require_approval() {
+            if let Some(peer_info) = dtls_server.peer_info(conn_idx.index()) {
+                errors.send(DtlsServerEvent::ConnRequested { 
+                    conn_index: conn_idx.index(), 
+                    peer_addr: peer_info.remote_addr, 
+                    peer_cert: peer_info.certificates
+                    .into_iter()
+                    .next()
+                });
+            }
+            continue;
+        }
 
-        if let Err(e) = dtls_server.start_conn(conn_idx) {
+        if let Err(e) = dtls_server.approve(conn_idx.index()) {
+            warn!("conn {conn_idx:?} could not be started: {e}");
             errors.send(DtlsServerEvent::Error { 
-                err: anyhow!("conn {conn_idx:?} could not be started: {e}") 
+                err: e 
             });
-
-            continue;
         }
+    }
 
+    // renet client is allocated only for approved conns
+    while let Some(conn_idx) = dtls_server.approve_check() {
         debug!("conn: {conn_idx:?} has been started from renet-dtls system");
 
-        renet_server.add_connection(conn_idx.to_renet_id());
+        // added to renet when its token is validated
+        match token_auth {
+            Some(ref mut auth) => {
+                auth.pending.insert(conn_idx.index(), Instant::now());
+            }
+            None => renet_server.add_connection(conn_idx.to_renet_id())
+        }
+
+        if let Some(peer_info) = dtls_server.peer_info(conn_idx.index()) {
+            errors.send(DtlsServerEvent::ConnAccepted { 
+                conn_index: conn_idx.index(), 
+                peer_info 
+            });
+        }
     }
 }
 
 fn recv_system(
     mut renet_server: ResMut<RenetServer>,
     mut dtls_server: ResMut<DtlsServer>,
-    mut errors: EventWriter<DtlsServerEvent>
+    mut token_auth: Option<ResMut<DtlsTokenAuth>>,
+    mut errors: EventWriter<DtlsServerEvent>,
+    mut token_events: EventWriter<DtlsTokenEvent>
 ) {
     if dtls_server.is_closed() {
         return;
@@ -82,18 +189,118 @@ fn recv_system(
             return;
         };
 
+        if let Some(ref mut auth) = token_auth {
+            let conn_index = conn_idx.index();
+            if auth.pending.remove(&conn_index).is_some() {
+                auth_token(
+                    &mut renet_server, 
+                    &mut dtls_server, 
+                    auth, 
+                    conn_index, 
+                    &bytes, 
+                    &mut token_events
+                );
+                continue;
+            }
+
+            // resent by client because ack was lost
+            if token::is_token(&bytes) {
+                if let Err(e) = dtls_server.send(conn_index, Bytes::from_static(token::token_ack())) {
+                    debug!("could not ack token of conn {conn_index}: {e}");
+                }
+                continue;
+            }
+        }
+
         if let Err(e) = renet_server.process_packet_from(
             &bytes, 
             conn_idx.to_renet_id()
         ) {
+            warn!("error on receiving conn {conn_idx:?}: {e}");
             errors.send(DtlsServerEvent::ConnError { 
                 conn_index: conn_idx.index(), 
-                err: anyhow!("error on receiving conn {conn_idx:?}: {e}")
+                err: DtlsError::Renet(e.to_string())
             });
         }
     }
 }
 
+// first datagram of conn has to be a valid token
+fn auth_token(
+    renet_server: &mut RenetServer,
+    dtls_server: &mut DtlsServer,
+    auth: &mut DtlsTokenAuth,
+    conn_index: u64,
+    bytes: &[u8],
+    token_events: &mut EventWriter<DtlsTokenEvent>
+) {
+    let Some(peer_info) = dtls_server.peer_info(conn_index) else {
+        warn!("conn {conn_index} is closed before its token is validated");
+        dtls_server.disconnect_with_reason(conn_index, DisconnectReason::Unauthorized);
+        token_events.send(DtlsTokenEvent::Rejected { 
+            conn_index, 
+            err: DtlsTokenError::ConnClosed 
+        });
+        return;
+    };
+
+    let result = auth.validate(bytes, peer_info.remote_addr)
+    .and_then(|token| {
+        auth.claim(token.client_id, conn_index, |idx| dtls_server.has_conn(idx))
+        .map(|_| token)
+    });
+
+    match result {
+        Ok(token) => {
+            renet_server.add_connection(ClientId::from_raw(conn_index));
+            if let Err(e) = dtls_server.send(conn_index, Bytes::from_static(token::token_ack())) {
+                debug!("could not ack token of conn {conn_index}: {e}");
+            }
+
+            debug!("token of client {} is accepted as conn {conn_index}", token.client_id);
+            token_events.send(DtlsTokenEvent::Accepted { 
+                conn_index, 
+                client_id: token.client_id, 
+                user_data: Box::new(token.user_data) 
+            });
+        }
+        Err(err) => {
+            warn!("token from conn {conn_index} is rejected: {err}");
+            dtls_server.disconnect_with_reason(conn_index, DisconnectReason::Unauthorized);
+            token_events.send(DtlsTokenEvent::Rejected { 
+                conn_index, 
+                err 
+            });
+        }
+    }
+}
+
+fn token_timeout_system(
+    mut dtls_server: ResMut<DtlsServer>,
+    mut token_auth: ResMut<DtlsTokenAuth>,
+    mut token_events: EventWriter<DtlsTokenEvent>
+) {
+    // closed before sending token
+    token_auth.pending.retain(|idx, _| dtls_server.has_conn(*idx));
+    token_auth.clients.retain(|_, idx| dtls_server.has_conn(*idx));
+
+    let timeout = Duration::from_secs(token_auth.config.timeout_secs);
+    let expired = token_auth.pending.iter()
+    .filter(|(_, t)| t.elapsed() >= timeout)
+    .map(|(idx, _)| *idx)
+    .collect::<Vec<_>>();
+
+    for conn_index in expired {
+        token_auth.pending.remove(&conn_index);
+        warn!("conn {conn_index} did not send token in time");
+        dtls_server.disconnect_with_reason(conn_index, DisconnectReason::Unauthorized);
+        token_events.send(DtlsTokenEvent::Rejected { 
+            conn_index, 
+            err: DtlsTokenError::Timeout 
+        });
+    }
+}
+
 fn send_system(
     mut renet_server: ResMut<RenetServer>,
     dtls_server: Res<DtlsServer>,
@@ -114,7 +321,7 @@ fn send_system(
             if let Err(e) = dtls_server.send(client_id.raw(), Bytes::from(pkt)) {
                 errors.send(DtlsServerEvent::ConnError { 
                     conn_index: client_id.raw(), 
-                    err: anyhow!("error on sending to conn {client_id}: {e}") 
+                    err: e 
                 });
 
                 continue 'client_loop;
@@ -123,11 +330,75 @@ fn send_system(
     }
 }
 
+fn queue_system(
+    mut renet_server: ResMut<RenetServer>,
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    for full in dtls_server.queue_check() {
+        let conn_index = full.conn_index.index();
+        if full.disconnect {
+            warn!("conn {conn_index} overflowed its queue, disconnecting");
+            renet_server.disconnect_dtls_with_reason(
+                &mut dtls_server, 
+                conn_index, 
+                DisconnectReason::QueueFull
+            );
+        }
+
+        dtls_events.send(DtlsServerEvent::QueueFull { 
+            conn_index, 
+            dropped: full.dropped 
+        });
+    }
+}
+
+fn drain_system(
+    mut renet_server: ResMut<RenetServer>,
+    mut dtls_server: ResMut<DtlsServer>,
+    mut dtls_events: EventWriter<DtlsServerEvent>
+) {
+    let Some(drain) = dtls_server.drain_check() else {
+        return;
+    };
+
+    match drain {
+        DtlsServerDrain::Remaining(remaining) => {
+            dtls_events.send(DtlsServerEvent::Draining { 
+                remaining 
+            });
+        }
+        DtlsServerDrain::DeadlinePassed(remaining) => {
+            warn!("drain deadline passed, disconnecting {remaining} conns");
+            renet_server.disconnect_all_dtls_with_reason(
+                &mut dtls_server, 
+                DisconnectReason::ServerShutdown
+            );
+            dtls_events.send(DtlsServerEvent::DrainDeadline { 
+                remaining 
+            });
+        }
+        DtlsServerDrain::Drained => {
+            dtls_events.send(DtlsServerEvent::Drained);
+        }
+    }
+}
+
 pub struct RenetDtlsServerPlugin {
     pub max_clients: usize,
     pub buf_size: usize,
     pub send_timeout_secs: u64,
-    pub recv_timeout_secs: Option<u64>
+    pub recv_timeout_secs: Option<u64>,
+    pub queue: DtlsQueueConfig,
+    // acpted conns are reported as ConnRequested
+    // and added to renet after DtlsServer::approve if true
+    pub require_approval: bool,
+    // pending conns are rejected with ApprovalTimeout after this if some
+    pub pending_timeout_secs: Option<u64>,
+    // clients have to send DtlsConnectToken first if some.
+    // renet ClientId stays conn_index, unlike netcode,
+    // client_id of token comes with DtlsTokenEvent::Accepted
+    pub token: Option<DtlsTokenConfig>
 }
 
 impl Plugin for RenetDtlsServerPlugin {
@@ -138,18 +409,28 @@ impl Plugin for RenetDtlsServerPlugin {
             info!("crypto provider already exists");
         }
 
-        let dtls_server = match DtlsServer::new(
+        let mut dtls_server = match DtlsServer::new(
             self.max_clients,
             self.buf_size,
             self.send_timeout_secs,
-            self.recv_timeout_secs
+            self.recv_timeout_secs,
+            self.queue,
+            // renet measures rtt by itself
+            None
         ) {
             Ok(s) => s,
             Err(e) => panic!("{e}")
         };
+        dtls_server.set_require_approval(self.require_approval);
+        dtls_server.set_pending_timeout_secs(self.pending_timeout_secs);
+
+        if let Some(ref config) = self.token {
+            app.insert_resource(DtlsTokenAuth::new(config.clone()));
+        }
 
         app.insert_resource(dtls_server)
         .add_event::<DtlsServerEvent>()
+        .add_event::<DtlsTokenEvent>()
         .configure_sets(PreUpdate, DtlsSet::Recv.before(RenetReceive))
         .configure_sets(PreUpdate, DtlsSet::Acpt.before(DtlsSet::Recv))
         .configure_sets(PostUpdate, DtlsSet::Send.after(RenetSend))
@@ -170,10 +451,19 @@ impl Plugin for RenetDtlsServerPlugin {
         )
         .add_systems(PostUpdate, (
             event::health_event_system,
-            event::timeout_event_system
+            event::timeout_event_system,
+            event::reject_event_system,
+            queue_system
+            .run_if(resource_exists::<RenetServer>),
+            drain_system
+            .run_if(resource_exists::<RenetServer>)
         )
             .chain()
             .after(DtlsSet::Send)
+        )
+        .add_systems(PostUpdate, 
+            token_timeout_system
+            .run_if(resource_exists::<DtlsTokenAuth>)
         );
     }
 }
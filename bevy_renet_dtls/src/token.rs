@@ -0,0 +1,483 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH}
+};
+use bevy::prelude::*;
+use bytes::{Buf, BufMut, BytesMut};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use thiserror::Error;
+
+// connect token layer modeled on netcode ConnectToken, with one deviation:
+// renet ClientId of a conn is its dtls conn_index, not client_id of token.
+// client_id is reported by DtlsTokenEvent::Accepted together with conn_index,
+// so application has to map them to find the player of a renet client
+
+pub const TOKEN_USER_DATA_BYTES: usize = 256;
+pub const TOKEN_KEY_BYTES: usize = 32;
+
+// renet packets start with packet type byte, never with these
+const TOKEN_MAGIC: &[u8; 4] = b"RDTK";
+const TOKEN_ACK_MAGIC: &[u8; 4] = b"RDTA";
+const MAC_BYTES: usize = 32;
+// allowed clock difference between matchmaker and server
+const TOKEN_CLOCK_SKEW_SECS: u64 = 60;
+const TOKEN_BYTES: usize = 4 + 8 * 4 + TOKEN_USER_DATA_BYTES + MAC_BYTES;
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum DtlsTokenError {
+    #[error("token is malformed")]
+    Malformed,
+    #[error("token signature is invalid")]
+    InvalidSignature,
+    #[error("token protocol id {0} does not match")]
+    ProtocolMismatch(u64),
+    #[error("token is expired")]
+    Expired,
+    #[error("token is created after its expiry or in the future")]
+    InvalidTimestamp,
+    #[error("token is already used from another ip")]
+    AlreadyUsed,
+    #[error("client id {0} is already connected")]
+    ClientIdInUse(u64),
+    #[error("conn is closed before token is validated")]
+    ConnClosed,
+    #[error("first datagram is not a token")]
+    NotToken,
+    #[error("token is not received in time")]
+    Timeout
+}
+
+// issued by matchmaker and sent by client as its first datagram,
+// signed with private key shared by matchmaker and server.
+// dtls already encrypts conn, so token is signed but not encrypted
+#[derive(Clone, Debug)]
+pub struct DtlsConnectToken {
+    pub protocol_id: u64,
+    // chosen by matchmaker, only one live conn can use it.
+    // renet client id is conn_index instead, 
+    // DtlsTokenEvent::Accepted tells which conn_index it is
+    pub client_id: u64,
+    // unix secs
+    pub create_timestamp: u64,
+    pub expire_timestamp: u64,
+    pub user_data: [u8; TOKEN_USER_DATA_BYTES],
+    mac: [u8; MAC_BYTES]
+}
+
+impl DtlsConnectToken {
+    // current_time is duration since unix epoch, as netcode ConnectToken
+    pub fn generate(
+        current_time: Duration,
+        protocol_id: u64,
+        expire_seconds: u64,
+        client_id: u64,
+        user_data: Option<&[u8; TOKEN_USER_DATA_BYTES]>,
+        private_key: &[u8; TOKEN_KEY_BYTES]
+    ) -> Self {
+        let create_timestamp = current_time.as_secs();
+        let mut token = Self{
+            protocol_id,
+            client_id,
+            create_timestamp,
+            expire_timestamp: create_timestamp.saturating_add(expire_seconds),
+            user_data: match user_data {
+                Some(d) => *d,
+                None => [0; TOKEN_USER_DATA_BYTES]
+            },
+            mac: [0; MAC_BYTES]
+        };
+
+        let mut buf = BytesMut::with_capacity(TOKEN_BYTES);
+        token.write_body(&mut buf);
+        token.mac.copy_from_slice(&sign(private_key, &buf)
+            .finalize()
+            .into_bytes()
+        );
+        token
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = BytesMut::with_capacity(TOKEN_BYTES);
+        self.write_body(&mut buf);
+        buf.put_slice(&self.mac);
+        buf.to_vec()
+    }
+
+    // parses only, call verify before trusting it
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DtlsTokenError> {
+        if bytes.len() != TOKEN_BYTES || !is_token(bytes) {
+            return Err(DtlsTokenError::Malformed);
+        }
+
+        let mut buf = &bytes[TOKEN_MAGIC.len()..];
+        let protocol_id = buf.get_u64();
+        let client_id = buf.get_u64();
+        let create_timestamp = buf.get_u64();
+        let expire_timestamp = buf.get_u64();
+        let mut user_data = [0; TOKEN_USER_DATA_BYTES];
+        buf.copy_to_slice(&mut user_data);
+        let mut mac = [0; MAC_BYTES];
+        buf.copy_to_slice(&mut mac);
+
+        Ok(Self{
+            protocol_id,
+            client_id,
+            create_timestamp,
+            expire_timestamp,
+            user_data,
+            mac
+        })
+    }
+
+    pub fn verify(
+        &self,
+        protocol_id: u64,
+        current_time: Duration,
+        private_key: &[u8; TOKEN_KEY_BYTES]
+    ) -> Result<(), DtlsTokenError> {
+        let mut buf = BytesMut::with_capacity(TOKEN_BYTES);
+        self.write_body(&mut buf);
+        // constant time comparison
+        if sign(private_key, &buf)
+        .verify_slice(&self.mac)
+        .is_err() {
+            return Err(DtlsTokenError::InvalidSignature);
+        }
+
+        if self.protocol_id != protocol_id {
+            return Err(DtlsTokenError::ProtocolMismatch(self.protocol_id));
+        }
+        let now = current_time.as_secs();
+        if self.create_timestamp > self.expire_timestamp
+        || self.create_timestamp > now.saturating_add(TOKEN_CLOCK_SKEW_SECS) {
+            return Err(DtlsTokenError::InvalidTimestamp);
+        }
+        if now >= self.expire_timestamp {
+            return Err(DtlsTokenError::Expired);
+        }
+        Ok(())
+    }
+
+    fn write_body(&self, buf: &mut BytesMut) {
+        buf.put_slice(TOKEN_MAGIC);
+        buf.put_u64(self.protocol_id);
+        buf.put_u64(self.client_id);
+        buf.put_u64(self.create_timestamp);
+        buf.put_u64(self.expire_timestamp);
+        buf.put_slice(&self.user_data);
+    }
+}
+
+#[inline]
+fn sign(private_key: &[u8; TOKEN_KEY_BYTES], body: &[u8]) -> HmacSha256 {
+    // hmac accepts key of any length
+    let mut mac = HmacSha256::new_from_slice(private_key)
+    .unwrap();
+    mac.update(body);
+    mac
+}
+
+#[inline]
+pub(crate) fn is_token(bytes: &[u8]) -> bool {
+    bytes.starts_with(TOKEN_MAGIC)
+}
+
+#[inline]
+pub(crate) fn is_token_ack(bytes: &[u8]) -> bool {
+    bytes == TOKEN_ACK_MAGIC
+}
+
+#[inline]
+pub(crate) fn token_ack() -> &'static [u8] {
+    TOKEN_ACK_MAGIC
+}
+
+#[inline]
+pub(crate) fn unix_time() -> Duration {
+    SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+}
+
+// enables token layer on server
+#[derive(Clone)]
+pub struct DtlsTokenConfig {
+    pub protocol_id: u64,
+    pub private_key: [u8; TOKEN_KEY_BYTES],
+    // conn is disconnected if no token arrives in this time
+    pub timeout_secs: u64
+}
+
+#[derive(Event, Debug)]
+pub enum DtlsTokenEvent {
+    // renet connection is added with conn_index as its ClientId,
+    // client_id of token is not used by renet
+    Accepted {
+        conn_index: u64,
+        client_id: u64,
+        // boxed to keep event small
+        user_data: Box<[u8; TOKEN_USER_DATA_BYTES]>
+    },
+    // conn is disconnected
+    Rejected {
+        conn_index: u64,
+        err: DtlsTokenError
+    }
+}
+
+// started conns waiting for token, used tokens until expiry
+// and conn_index of each accepted client_id
+#[derive(Resource)]
+pub(crate) struct DtlsTokenAuth {
+    pub(crate) config: DtlsTokenConfig,
+    pub(crate) pending: HashMap<u64, Instant>,
+    pub(crate) clients: HashMap<u64, u64>,
+    used: HashMap<[u8; MAC_BYTES], (IpAddr, u64)>
+}
+
+impl DtlsTokenAuth {
+    #[inline]
+    pub(crate) fn new(config: DtlsTokenConfig) -> Self {
+        Self{
+            config,
+            pending: default(),
+            clients: default(),
+            used: default()
+        }
+    }
+
+    // verifies token and marks it used by ip of addr.
+    // same token from same ip is accepted again for reconnection,
+    // which comes from a new port
+    pub(crate) fn validate(&mut self, bytes: &[u8], addr: SocketAddr)
+    -> Result<DtlsConnectToken, DtlsTokenError> {
+        if !is_token(bytes) {
+            return Err(DtlsTokenError::NotToken);
+        }
+
+        let token = DtlsConnectToken::from_bytes(bytes)?;
+        let now = unix_time();
+        token.verify(
+            self.config.protocol_id,
+            now,
+            &self.config.private_key
+        )?;
+
+        let secs = now.as_secs();
+        self.used.retain(|_, (_, expire)| *expire > secs);
+        match self.used.get(&token.mac) {
+            Some((used_by, _)) if *used_by != addr.ip() => {
+                return Err(DtlsTokenError::AlreadyUsed);
+            }
+            _ => {
+                self.used.insert(token.mac, (addr.ip(), token.expire_timestamp));
+            }
+        }
+        Ok(token)
+    }
+
+    // binds client_id to conn_index, 
+    // rejected while another live conn has it
+    pub(crate) fn claim(
+        &mut self, 
+        client_id: u64, 
+        conn_index: u64, 
+        is_live: impl Fn(u64) -> bool
+    ) -> Result<(), DtlsTokenError> {
+        match self.clients.get(&client_id) {
+            Some(idx) if *idx != conn_index && is_live(*idx) => {
+                Err(DtlsTokenError::ClientIdInUse(client_id))
+            }
+            _ => {
+                self.clients.insert(client_id, conn_index);
+                Ok(())
+            }
+        }
+    }
+}
+
+// token sent by client until server acks it.
+// insert before start_dtls to enable token layer on client
+#[derive(Resource)]
+pub struct DtlsClientToken {
+    bytes: Vec<u8>,
+    pub(crate) accepted: bool,
+    pub(crate) last_sent: Option<Instant>
+}
+
+impl DtlsClientToken {
+    #[inline]
+    pub fn new(token: &DtlsConnectToken) -> Self {
+        Self{
+            bytes: token.to_bytes(),
+            accepted: false,
+            last_sent: None
+        }
+    }
+
+    #[inline]
+    pub fn is_accepted(&self) -> bool {
+        self.accepted
+    }
+
+    #[inline]
+    pub(crate) fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // called on each connection, token is sent again from scratch
+    #[inline]
+    pub(crate) fn reset(&mut self) {
+        self.accepted = false;
+        self.last_sent = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROTOCOL_ID: u64 = 7;
+    const KEY: [u8; TOKEN_KEY_BYTES] = [3; TOKEN_KEY_BYTES];
+
+    fn token(current_time: Duration) -> DtlsConnectToken {
+        DtlsConnectToken::generate(current_time, PROTOCOL_ID, 30, 42, None, &KEY)
+    }
+
+    fn resign(token: &mut DtlsConnectToken) {
+        let mut buf = BytesMut::with_capacity(TOKEN_BYTES);
+        token.write_body(&mut buf);
+        token.mac.copy_from_slice(&sign(&KEY, &buf)
+            .finalize()
+            .into_bytes()
+        );
+    }
+
+    fn auth() -> DtlsTokenAuth {
+        DtlsTokenAuth::new(DtlsTokenConfig{
+            protocol_id: PROTOCOL_ID,
+            private_key: KEY,
+            timeout_secs: 5
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let now = unix_time();
+        let mut user_data = [0; TOKEN_USER_DATA_BYTES];
+        user_data[0] = 9;
+        let token = DtlsConnectToken::generate(now, PROTOCOL_ID, 30, 42, Some(&user_data), &KEY);
+
+        let bytes = token.to_bytes();
+        assert_eq!(bytes.len(), TOKEN_BYTES);
+        let parsed = DtlsConnectToken::from_bytes(&bytes)
+        .unwrap();
+        assert_eq!(parsed.verify(PROTOCOL_ID, now, &KEY), Ok(()));
+        assert_eq!(parsed.client_id, 42);
+        assert_eq!(parsed.user_data, user_data);
+        assert_eq!(parsed.expire_timestamp, now.as_secs() + 30);
+    }
+
+    #[test]
+    fn tampered_body_or_mac() {
+        let now = unix_time();
+        let bytes = token(now).to_bytes();
+
+        // client_id
+        let mut body = bytes.clone();
+        body[TOKEN_MAGIC.len() + 8] ^= 1;
+        let parsed = DtlsConnectToken::from_bytes(&body)
+        .unwrap();
+        assert_eq!(parsed.verify(PROTOCOL_ID, now, &KEY), Err(DtlsTokenError::InvalidSignature));
+
+        let mut mac = bytes;
+        *mac.last_mut().unwrap() ^= 1;
+        let parsed = DtlsConnectToken::from_bytes(&mac)
+        .unwrap();
+        assert_eq!(parsed.verify(PROTOCOL_ID, now, &KEY), Err(DtlsTokenError::InvalidSignature));
+
+        let parsed = token(now);
+        assert_eq!(
+            parsed.verify(PROTOCOL_ID, now, &[4; TOKEN_KEY_BYTES]), 
+            Err(DtlsTokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn wrong_protocol() {
+        let now = unix_time();
+        assert_eq!(
+            token(now).verify(PROTOCOL_ID + 1, now, &KEY), 
+            Err(DtlsTokenError::ProtocolMismatch(PROTOCOL_ID))
+        );
+    }
+
+    #[test]
+    fn expired() {
+        let now = unix_time();
+        let created = now - Duration::from_secs(60);
+        assert_eq!(token(created).verify(PROTOCOL_ID, now, &KEY), Err(DtlsTokenError::Expired));
+    }
+
+    #[test]
+    fn invalid_timestamp() {
+        let now = unix_time();
+        let future = now + Duration::from_secs(TOKEN_CLOCK_SKEW_SECS + 60);
+        assert_eq!(
+            token(future).verify(PROTOCOL_ID, now, &KEY), 
+            Err(DtlsTokenError::InvalidTimestamp)
+        );
+
+        let mut t = token(now);
+        t.expire_timestamp = t.create_timestamp - 1;
+        resign(&mut t);
+        assert_eq!(t.verify(PROTOCOL_ID, now, &KEY), Err(DtlsTokenError::InvalidTimestamp));
+    }
+
+    #[test]
+    fn wrong_length() {
+        let bytes = token(unix_time()).to_bytes();
+        assert!(matches!(
+            DtlsConnectToken::from_bytes(&bytes[..TOKEN_BYTES - 1]), 
+            Err(DtlsTokenError::Malformed)
+        ));
+
+        let mut longer = bytes;
+        longer.push(0);
+        assert!(matches!(
+            DtlsConnectToken::from_bytes(&longer), 
+            Err(DtlsTokenError::Malformed)
+        ));
+    }
+
+    #[test]
+    fn used_from_second_addr() {
+        let mut auth = auth();
+        let bytes = token(unix_time()).to_bytes();
+        let addr: SocketAddr = "127.0.0.1:4000".parse()
+        .unwrap();
+        let reconnected: SocketAddr = "127.0.0.1:4001".parse()
+        .unwrap();
+        let other: SocketAddr = "127.0.0.2:4000".parse()
+        .unwrap();
+
+        assert!(auth.validate(&bytes, addr).is_ok());
+        // reconnection from new port
+        assert!(auth.validate(&bytes, reconnected).is_ok());
+        assert!(matches!(auth.validate(&bytes, other), Err(DtlsTokenError::AlreadyUsed)));
+    }
+
+    #[test]
+    fn client_id_in_use() {
+        let mut auth = auth();
+        assert_eq!(auth.claim(42, 1, |_| true), Ok(()));
+        assert_eq!(auth.claim(42, 1, |_| true), Ok(()));
+        assert_eq!(auth.claim(42, 2, |_| true), Err(DtlsTokenError::ClientIdInUse(42)));
+        // first conn is closed
+        assert_eq!(auth.claim(42, 2, |_| false), Ok(()));
+    }
+}
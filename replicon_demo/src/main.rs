@@ -110,8 +110,12 @@ fn read_cli(
                 cert_option: ServerCertOption::LoadWithClientAuth { 
                     priv_key_path: "my_certificates/server.priv.pem", 
                     certificate_path: "my_certificates/server.pub.pem",
-                    client_ca_path: "my_certificates/server.pub.pem" 
-                }
+                    client_ca_path: "my_certificates/server.pub.pem",
+                    client_verifier: None
+                },
+                tuning: default(),
+                limits: default(),
+                ip_filter: default()
             })?;
 
             commands.insert_resource(server);
@@ -151,7 +155,8 @@ fn read_cli(
                         priv_key_path: "my_certificates/client.priv.pem", 
                         certificate_path: "my_certificates/client.pub.pem",
                         root_ca_path: "my_certificates/server.pub.pem" 
-                    }
+                    },
+                    tuning: default()
                 }
             )?;
 
@@ -288,10 +293,17 @@ fn main() {
             buf_size: 1500,
             send_timeout_secs: 10,
             recv_timeout_secs: None,
+            queue: default(),
+            require_approval: false,
+            pending_timeout_secs: Some(10),
+            token: None
         },
         RenetDtlsClientPlugin{
             timeout_secs: 10,
             buf_size: 1500,
+            recv_timeout_secs: None,
+            heartbeat_secs: None,
+            queue: default(),
         },
         SimpleBoxPlugin,
     ))